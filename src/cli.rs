@@ -0,0 +1,431 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use gchat_mbox_extractor::{
+    AttachmentFilter, CsvFormat, DiagnosticKind, DocumentUnit, ExportFormat, ExtractionOptions, ExtractionSummary,
+    Extractor, ExtractorError, LineEnding, LoadFileFormat, MboxSplitOptions, MboxSplitSummary, MessageColumn, PdfFormat,
+    QuoteStyle, SlackFormat, SplitBy, TranscriptFormat, DEFAULT_ATTACHMENT_TEMPLATE,
+    DEFAULT_TRANSCRIPT_TIMESTAMP_FORMAT, split_mbox,
+};
+
+/// The extraction finished without losing any data.
+const EXIT_SUCCESS: u8 = 0;
+/// The extraction could not be completed.
+const EXIT_FAILURE: u8 = 1;
+/// The extraction finished, but entries, messages or attachments were skipped (see `diagnostics.json`).
+const EXIT_PARTIAL: u8 = 3;
+
+/// Extracts messages and attachments from Google Chat .mbox exports.
+///
+/// Run without arguments to open the graphical interface (when built with the `gui` feature).
+#[derive(Parser)]
+#[command(name = "gchat_mbox_extractor", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Extract an .mbox file without opening the graphical interface
+    Extract(Box<ExtractArgs>),
+    /// Write the matching entries of an .mbox file to new .mbox files, copied byte for byte
+    Split(SplitArgs),
+}
+
+#[derive(Args)]
+struct ExtractArgs {
+    /// The .mbox file to extract
+    input: PathBuf,
+
+    /// The folder receiving messages.csv, attachments.csv/json, diagnostics.json and attachments
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Additional outputs to write next to messages.csv (comma-separated: xlsx, json, ndjson, sqlite, parquet, arrow, html, markdown, text, pdf, loadfile, eml, mbox, slack)
+    #[arg(short, long, value_name = "FORMATS", value_delimiter = ',')]
+    format: Vec<ExportFormat>,
+
+    /// Write attachments to the attachments folder
+    #[arg(short, long)]
+    attachments: bool,
+
+    /// Abort the run on the first attachment that cannot be written instead of skipping it
+    #[arg(long)]
+    fail_on_attachment_error: bool,
+
+    /// Store identical attachments once, named by their SHA-256 hash
+    #[arg(long)]
+    deduplicate: bool,
+
+    /// Layout of the attachments folder, e.g. "{space}/{yyyy}/{mm}/{sender}/{filename}"
+    #[arg(long, value_name = "TEMPLATE", default_value = DEFAULT_ATTACHMENT_TEMPLATE)]
+    attachment_layout: String,
+
+    /// Only write attachments of these MIME types (comma-separated, e.g. "image/*,application/pdf")
+    #[arg(long, value_name = "TYPES", value_delimiter = ',')]
+    allow_type: Vec<String>,
+
+    /// Never write attachments of these MIME types (comma-separated)
+    #[arg(long, value_name = "TYPES", value_delimiter = ',')]
+    deny_type: Vec<String>,
+
+    /// Only write attachments with these extensions (comma-separated, e.g. "pdf,docx")
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    allow_extension: Vec<String>,
+
+    /// Never write attachments with these extensions (comma-separated)
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    deny_extension: Vec<String>,
+
+    /// Do not write attachments larger than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_attachment_size: Option<usize>,
+
+    /// Only write attachments whose filename matches one of these glob patterns (comma-separated)
+    #[arg(long, value_name = "PATTERNS", value_delimiter = ',')]
+    include_name: Vec<String>,
+
+    /// Never write attachments whose filename matches one of these glob patterns (comma-separated)
+    #[arg(long, value_name = "PATTERNS", value_delimiter = ',')]
+    exclude_name: Vec<String>,
+
+    /// Character separating CSV fields, e.g. ";" or "tab"
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_csv_char)]
+    csv_delimiter: char,
+
+    /// Character enclosing quoted CSV fields
+    #[arg(long, value_name = "CHAR", default_value = "\"", value_parser = parse_csv_char)]
+    csv_quote: char,
+
+    /// Line ending of the CSV files: "crlf" (RFC 4180) or "lf"
+    #[arg(long, value_name = "ENDING", default_value = "crlf")]
+    csv_line_ending: LineEnding,
+
+    /// Which CSV fields are quoted: "always" or "necessary"
+    #[arg(long, value_name = "STYLE", default_value = "always")]
+    csv_quote_style: QuoteStyle,
+
+    /// Start the CSV files with a UTF-8 byte order mark, so Excel detects the encoding
+    #[arg(long)]
+    csv_bom: bool,
+
+    /// Columns of messages.csv, in order (comma-separated: message_id, sender, timestamp, content)
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',', default_value = "message_id,sender,timestamp,content")]
+    columns: Vec<MessageColumn>,
+
+    /// Roll messages.csv into numbered files of at most ROWS rows, header included (default: Excel's 1048576)
+    #[arg(long, value_name = "ROWS", num_args = 0..=1, default_missing_value = "1048576")]
+    split_csv: Option<usize>,
+
+    /// Prefix CSV cells starting with =, +, -, @, tab or carriage return with ' so spreadsheets do not run them as formulas
+    #[arg(long)]
+    neutralize_formulas: bool,
+
+    /// Write the Markdown transcripts as one file per conversation and month
+    #[arg(long)]
+    markdown_by_month: bool,
+
+    /// Wrap the plain-text transcripts at WIDTH characters (0 to never wrap)
+    #[arg(long, value_name = "WIDTH", default_value_t = 100)]
+    text_width: usize,
+
+    /// strftime format of the plain-text transcript timestamps, e.g. "%d.%m.%Y %H:%M"
+    #[arg(long, value_name = "FORMAT", default_value = DEFAULT_TRANSCRIPT_TIMESTAMP_FORMAT)]
+    text_timestamp_format: String,
+
+    /// Text before the Bates number of every PDF page, e.g. "ACME"
+    #[arg(long, value_name = "PREFIX", default_value = "")]
+    bates_prefix: String,
+
+    /// Bates number of the first PDF page; numbering continues across the transcripts
+    #[arg(long, value_name = "NUMBER", default_value_t = 1)]
+    bates_start: u64,
+
+    /// Minimum number of digits of the Bates numbers
+    #[arg(long, value_name = "DIGITS", default_value_t = 6)]
+    bates_digits: usize,
+
+    /// TrueType font (.ttf) to embed in the PDF transcripts before the bundled ones, e.g. for CJK text (repeatable)
+    #[arg(long, value_name = "FILE")]
+    pdf_font: Vec<PathBuf>,
+
+    /// What a document of the load files is: "conversation" or "message"
+    #[arg(long, value_name = "UNIT", default_value = "conversation")]
+    loadfile_unit: DocumentUnit,
+
+    /// Custodian named in the load files (default: the .mbox filename)
+    #[arg(long, value_name = "NAME", default_value = "")]
+    custodian: String,
+
+    /// Text before the control number of every load file document, e.g. "ACME"
+    #[arg(long, value_name = "PREFIX", default_value = "DOC")]
+    control_prefix: String,
+
+    /// Control number of the first load file document
+    #[arg(long, value_name = "NUMBER", default_value_t = 1)]
+    control_start: u64,
+
+    /// Minimum number of digits of the control numbers
+    #[arg(long, value_name = "DIGITS", default_value_t = 8)]
+    control_digits: usize,
+
+    /// URL the attachments folder is published at, so Slack's importer can download the files (default: paths)
+    #[arg(long, value_name = "URL", default_value = "")]
+    slack_file_url: String,
+
+    /// Do not print progress to stderr
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Args)]
+struct SplitArgs {
+    /// The .mbox file to split
+    input: PathBuf,
+
+    /// The folder receiving the .mbox files
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Write a file per "conversation", "year", "month" or "sender" instead of a single file
+    #[arg(long, value_name = "GROUP")]
+    by: Option<SplitBy>,
+
+    /// Continue in a new, numbered file once a file would grow beyond this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_size: Option<u64>,
+
+    /// Only keep entries of these spaces or direct messages (comma-separated)
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    conversation: Vec<String>,
+
+    /// Only keep entries with a message from a sender containing one of these texts (comma-separated)
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    sender: Vec<String>,
+
+    /// Only keep entries from this day on (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    since: Option<NaiveDate>,
+
+    /// Only keep entries up to and including this day (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    until: Option<NaiveDate>,
+}
+
+impl SplitArgs {
+    /// Converts the command-line flags into the options of the splitter
+    fn to_options(&self) -> MboxSplitOptions {
+        MboxSplitOptions::default()
+            .split_by(self.by)
+            .max_file_size(self.max_size)
+            .conversations(self.conversation.clone())
+            .senders(self.sender.clone())
+            .since(self.since)
+            .until(self.until)
+    }
+}
+
+impl ExtractArgs {
+    /// Converts the command-line flags into the options of the extraction pipeline
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExtractorError::InvalidOption` if an attachment name pattern is not a valid glob.
+    fn to_options(&self) -> Result<ExtractionOptions, ExtractorError> {
+        let attachment_filter: AttachmentFilter = AttachmentFilter::default()
+            .allowed_mime_types(self.allow_type.clone())
+            .denied_mime_types(self.deny_type.clone())
+            .allowed_extensions(self.allow_extension.clone())
+            .denied_extensions(self.deny_extension.clone())
+            .max_size(self.max_attachment_size)
+            .include_patterns(&self.include_name)
+            .and_then(|filter: AttachmentFilter| filter.exclude_patterns(&self.exclude_name))
+            .map_err(ExtractorError::InvalidOption)?;
+
+        Ok(ExtractionOptions::default()
+            .export_attachments(self.attachments)
+            .export_formats(self.format.clone())
+            .continue_on_error(!self.fail_on_attachment_error)
+            .deduplicate_attachments(self.deduplicate)
+            .attachment_path_template(self.attachment_layout.clone())
+            .attachment_filter(attachment_filter)
+            .csv_format(CsvFormat::default()
+                .delimiter(self.csv_delimiter)
+                .quote(self.csv_quote)
+                .line_ending(self.csv_line_ending)
+                .quote_style(self.csv_quote_style)
+                .byte_order_mark(self.csv_bom)
+                .message_columns(self.columns.clone())
+                .max_rows_per_file(self.split_csv)
+                .neutralize_formulas(self.neutralize_formulas))
+            .transcript_format(TranscriptFormat::default()
+                .split_by_month(self.markdown_by_month)
+                .line_width(self.text_width)
+                .timestamp_format(self.text_timestamp_format.clone()))
+            .pdf_format(PdfFormat::default()
+                .bates_prefix(self.bates_prefix.clone())
+                .bates_start(self.bates_start)
+                .bates_digits(self.bates_digits)
+                .fonts(self.pdf_font.clone()))
+            .load_file_format(LoadFileFormat::default()
+                .unit(self.loadfile_unit)
+                .custodian(self.custodian.clone())
+                .control_prefix(self.control_prefix.clone())
+                .control_start(self.control_start)
+                .control_digits(self.control_digits))
+            .slack_format(SlackFormat::default().file_base_url(self.slack_file_url.clone())))
+    }
+}
+
+/// Parses a single CSV character, accepting "tab" and "\t" for a tab
+fn parse_csv_char(value: &str) -> Result<char, String> {
+    if value.eq_ignore_ascii_case("tab") || value == "\\t" {
+        return Ok('\t');
+    }
+    let mut chars: std::str::Chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("expected a single character, got '{}'", value)),
+    }
+}
+
+/// The machine-readable summary printed to stdout once the run is over
+#[derive(Serialize)]
+struct RunReport {
+    status: &'static str,
+    exit_code: u8,
+    error: Option<String>,
+    input: PathBuf,
+    output: PathBuf,
+    summary: Option<ExtractionSummary>,
+    diagnostics: BTreeMap<DiagnosticKind, usize>,
+}
+
+/// The machine-readable summary printed to stdout once a split is over
+#[derive(Serialize)]
+struct SplitReport {
+    status: &'static str,
+    exit_code: u8,
+    error: Option<String>,
+    input: PathBuf,
+    output: PathBuf,
+    summary: Option<MboxSplitSummary>,
+}
+
+/// Parses the command line, runs the requested command and returns the process exit code.
+///
+/// Progress is printed to stderr and a JSON summary of the run to stdout.
+pub fn run() -> ExitCode {
+    let cli: Cli = Cli::parse();
+    match cli.command {
+        Command::Extract(args) => run_extract(&args),
+        Command::Split(args) => run_split(&args),
+    }
+}
+
+/// Runs the `extract` command
+fn run_extract(args: &ExtractArgs) -> ExitCode {
+    let options: Result<ExtractionOptions, ExtractorError> = args.to_options();
+    let mut extractor: Extractor = Extractor::new(options.as_ref().cloned().unwrap_or_default());
+    let mut last_reported: i32 = -5;
+
+    let result: Result<ExtractionSummary, ExtractorError> = options
+        .and_then(|_| std::fs::create_dir_all(&args.output).map_err(ExtractorError::from))
+        .and_then(|()| extractor.extract_with_progress(&args.input, &args.output, |progress: f32| {
+            let percent: i32 = (progress * 100.0).round() as i32;
+            if !args.quiet && percent / 5 > last_reported / 5 {
+                eprintln!("Progress: {:>3}%", percent);
+                last_reported = percent;
+            }
+        }));
+
+    let counts: BTreeMap<DiagnosticKind, usize> = extractor.diagnostics().counts();
+    let report: RunReport = match result {
+        Ok(summary) => {
+            let lost_data: bool = summary.attachments_failed > 0 || counts.keys().any(|kind: &DiagnosticKind| kind.is_data_loss());
+            if !args.quiet {
+                eprintln!("{}", summary.describe(extractor.diagnostics()));
+            }
+            RunReport {
+                status: if lost_data { "completed_with_errors" } else { "success" },
+                exit_code: if lost_data { EXIT_PARTIAL } else { EXIT_SUCCESS },
+                error: None,
+                input: args.input.clone(),
+                output: args.output.clone(),
+                summary: Some(summary),
+                diagnostics: counts,
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            RunReport {
+                status: "failed",
+                exit_code: EXIT_FAILURE,
+                error: Some(e.to_string()),
+                input: args.input.clone(),
+                output: args.output.clone(),
+                summary: None,
+                diagnostics: counts,
+            }
+        }
+    };
+
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to serialize summary: {}", e),
+    }
+    ExitCode::from(report.exit_code)
+}
+
+/// Runs the `split` command
+fn run_split(args: &SplitArgs) -> ExitCode {
+    let report: SplitReport = match split_mbox(&args.input, &args.output, &args.to_options()) {
+        Ok(summary) => {
+            eprintln!(
+                "{} of {} entries written to {} files",
+                summary.entries_written,
+                summary.entries,
+                summary.files.len(),
+            );
+            if summary.entries_unparsed > summary.entries_skipped {
+                eprintln!(
+                    "{} entries could not be parsed and were copied unchecked",
+                    summary.entries_unparsed - summary.entries_skipped,
+                );
+            }
+            if summary.entries_skipped > 0 {
+                eprintln!("{} entries could not be parsed and were left out by the filters", summary.entries_skipped);
+            }
+            SplitReport {
+                status: if summary.entries_skipped > 0 { "completed_with_errors" } else { "success" },
+                exit_code: if summary.entries_skipped > 0 { EXIT_PARTIAL } else { EXIT_SUCCESS },
+                error: None,
+                input: args.input.clone(),
+                output: args.output.clone(),
+                summary: Some(summary),
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            SplitReport {
+                status: "failed",
+                exit_code: EXIT_FAILURE,
+                error: Some(e.to_string()),
+                input: args.input.clone(),
+                output: args.output.clone(),
+                summary: None,
+            }
+        }
+    };
+
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to serialize summary: {}", e),
+    }
+    ExitCode::from(report.exit_code)
+}
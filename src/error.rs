@@ -0,0 +1,148 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Represents every error that can occur while extracting an .mbox file.
+///
+/// # Variants
+///
+/// * `Io` - An I/O operation (reading the .mbox file, writing outputs) failed.
+/// * `InvalidPath` - A path could not be represented as UTF-8 or is otherwise unusable.
+/// * `QuotedPrintable` - The HTML body of an entry could not be decoded from quoted-printable.
+/// * `Base64` - The content of an attachment could not be decoded from base64.
+/// * `Json` - Serializing an output document to JSON failed.
+/// * `MessageParse` - A required field could not be found in a raw message.
+/// * `Attachment` - An attachment part is missing its content type, filename or content.
+/// * `InvalidOption` - An extraction option has an unusable value.
+/// * `Xlsx` - Writing the Excel workbook failed.
+/// * `Sqlite` - Writing the SQLite database failed.
+/// * `Arrow` - Building the columns of a Parquet or Arrow IPC file failed.
+/// * `Parquet` - Writing a Parquet file failed.
+/// * `Pdf` - Writing a PDF transcript failed.
+#[derive(Debug)]
+pub enum ExtractorError {
+    Io(io::Error),
+    InvalidPath(PathBuf),
+    QuotedPrintable(quoted_printable::QuotedPrintableError),
+    Base64(base64::DecodeError),
+    Json(serde_json::Error),
+    MessageParse(String),
+    Attachment(String),
+    InvalidOption(String),
+    #[cfg(feature = "xlsx")]
+    Xlsx(rust_xlsxwriter::XlsxError),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    #[cfg(feature = "parquet")]
+    Arrow(arrow_schema::ArrowError),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    #[cfg(feature = "pdf")]
+    Pdf(printpdf::Error),
+}
+
+impl fmt::Display for ExtractorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractorError::Io(e) => write!(f, "I/O error: {}", e),
+            ExtractorError::InvalidPath(path) => write!(f, "Invalid path: {}", path.display()),
+            ExtractorError::QuotedPrintable(e) => write!(f, "Quoted-printable decoding failed: {}", e),
+            ExtractorError::Base64(e) => write!(f, "Base64 decoding failed: {}", e),
+            ExtractorError::Json(e) => write!(f, "JSON serialization failed: {}", e),
+            ExtractorError::MessageParse(reason) => write!(f, "Message could not be parsed: {}", reason),
+            ExtractorError::Attachment(reason) => write!(f, "Attachment could not be extracted: {}", reason),
+            ExtractorError::InvalidOption(reason) => write!(f, "Invalid option: {}", reason),
+            #[cfg(feature = "xlsx")]
+            ExtractorError::Xlsx(e) => write!(f, "Excel workbook could not be written: {}", e),
+            #[cfg(feature = "sqlite")]
+            ExtractorError::Sqlite(e) => write!(f, "SQLite database could not be written: {}", e),
+            #[cfg(feature = "parquet")]
+            ExtractorError::Arrow(e) => write!(f, "Arrow columns could not be built: {}", e),
+            #[cfg(feature = "parquet")]
+            ExtractorError::Parquet(e) => write!(f, "Parquet file could not be written: {}", e),
+            #[cfg(feature = "pdf")]
+            ExtractorError::Pdf(e) => write!(f, "PDF transcript could not be written: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExtractorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExtractorError::Io(e) => Some(e),
+            ExtractorError::QuotedPrintable(e) => Some(e),
+            ExtractorError::Base64(e) => Some(e),
+            ExtractorError::Json(e) => Some(e),
+            #[cfg(feature = "xlsx")]
+            ExtractorError::Xlsx(e) => Some(e),
+            #[cfg(feature = "sqlite")]
+            ExtractorError::Sqlite(e) => Some(e),
+            #[cfg(feature = "parquet")]
+            ExtractorError::Arrow(e) => Some(e),
+            #[cfg(feature = "parquet")]
+            ExtractorError::Parquet(e) => Some(e),
+            #[cfg(feature = "pdf")]
+            ExtractorError::Pdf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExtractorError {
+    fn from(e: io::Error) -> Self {
+        ExtractorError::Io(e)
+    }
+}
+
+impl From<quoted_printable::QuotedPrintableError> for ExtractorError {
+    fn from(e: quoted_printable::QuotedPrintableError) -> Self {
+        ExtractorError::QuotedPrintable(e)
+    }
+}
+
+impl From<base64::DecodeError> for ExtractorError {
+    fn from(e: base64::DecodeError) -> Self {
+        ExtractorError::Base64(e)
+    }
+}
+
+impl From<serde_json::Error> for ExtractorError {
+    fn from(e: serde_json::Error) -> Self {
+        ExtractorError::Json(e)
+    }
+}
+
+#[cfg(feature = "xlsx")]
+impl From<rust_xlsxwriter::XlsxError> for ExtractorError {
+    fn from(e: rust_xlsxwriter::XlsxError) -> Self {
+        ExtractorError::Xlsx(e)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for ExtractorError {
+    fn from(e: rusqlite::Error) -> Self {
+        ExtractorError::Sqlite(e)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<arrow_schema::ArrowError> for ExtractorError {
+    fn from(e: arrow_schema::ArrowError) -> Self {
+        ExtractorError::Arrow(e)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for ExtractorError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        ExtractorError::Parquet(e)
+    }
+}
+
+#[cfg(feature = "pdf")]
+impl From<printpdf::Error> for ExtractorError {
+    fn from(e: printpdf::Error) -> Self {
+        ExtractorError::Pdf(e)
+    }
+}
@@ -0,0 +1,342 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::ExtractorError;
+use crate::models::{Attachment, AttachmentRecord, Diagnostics, DiagnosticKind, ExportFormat, ExtractionOptions, ExtractionSummary, MboxEntry, Message};
+use crate::parsers::split_mbox_entries;
+use crate::sinks::{
+    export_sink, AttachmentFolderSink, AttachmentManifestSink, AttachmentSink, MessageSink, MessagesCsvSink, SinkContext,
+};
+use crate::utils::{read_mbox_file, decode_attachment, sha256_hex, sniff_content_type, write_diagnostics_to_json, path_to_str};
+
+const FILE_READ_WEIGHT: f32 = 0.1;
+const PARSING_WEIGHT: f32 = 0.2;
+const PROCESSING_WEIGHT: f32 = 0.6;
+const WRITING_WEIGHT: f32 = 0.1;
+
+/// Runs extractions of Google Chat .mbox files with a fixed set of options.
+///
+/// Every run writes `messages.csv`, the attachment manifest and `diagnostics.json` and, when exporting
+/// attachments, the `attachments` folder, plus one output per selected export format. Further outputs are
+/// produced by registering custom sinks (see `with_message_sink` and `with_attachment_sink`), which are
+/// driven in the same pass.
+///
+/// The diagnostics of the most recent run are kept until the next one starts, so they are
+/// available even when the run fails.
+///
+/// # Example
+///
+/// ```no_run
+/// use gchat_mbox_extractor::{ExtractionOptions, Extractor};
+///
+/// let options = ExtractionOptions::default()
+///     .export_attachments(true)
+///     .attachment_path_template("{space}/{yyyy}/{mm}/{filename}");
+/// let mut extractor = Extractor::new(options);
+/// let summary = extractor.extract("chat.mbox", "out")?;
+/// println!("{}", summary.describe(extractor.diagnostics()));
+/// # Ok::<(), gchat_mbox_extractor::ExtractorError>(())
+/// ```
+#[derive(Default)]
+pub struct Extractor {
+    options: ExtractionOptions,
+    diagnostics: Diagnostics,
+    message_sinks: Vec<Box<dyn MessageSink + Send>>,
+    attachment_sinks: Vec<Box<dyn AttachmentSink + Send>>,
+}
+
+impl Extractor {
+    pub fn new(options: ExtractionOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a sink receiving the messages and attachment records of every run, after the built-in ones.
+    pub fn with_message_sink(mut self, sink: impl MessageSink + Send + 'static) -> Self {
+        self.message_sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Adds a sink receiving the content of every exported attachment, after the built-in one.
+    ///
+    /// Attachment sinks are only driven when `export_attachments` is enabled.
+    pub fn with_attachment_sink(mut self, sink: impl AttachmentSink + Send + 'static) -> Self {
+        self.attachment_sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Returns the options used for every run.
+    pub fn options(&self) -> &ExtractionOptions {
+        &self.options
+    }
+
+    /// Returns the diagnostics recorded by the most recent run.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Extracts an .mbox file into the output folder (see `extract_with_progress`).
+    pub fn extract<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, mbox_path: P, output_path: Q) -> Result<ExtractionSummary, ExtractorError> {
+        self.extract_with_progress(mbox_path, output_path, |_| {})
+    }
+
+    /// Extracts an .mbox file into the output folder, reporting progress along the way.
+    ///
+    /// # Arguments
+    ///
+    /// * `mbox_path` - The path of the .mbox file to extract.
+    /// * `output_path` - The folder receiving `messages.csv`, the attachment manifest, `diagnostics.json` and `attachments`.
+    /// * `progress_callback` - A closure that will be called with the overall progress, from 0.0 to 1.0.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the summary of the run.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the .mbox file cannot be read, a message sink fails,
+    /// or an attachment cannot be written while `continue_on_error` is disabled.
+    pub fn extract_with_progress<P, Q, F>(&mut self, mbox_path: P, output_path: Q, progress_callback: F) -> Result<ExtractionSummary, ExtractorError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        F: FnMut(f32)
+    {
+        self.diagnostics.clear();
+
+        let mut messages_csv: MessagesCsvSink = MessagesCsvSink::new();
+        let mut attachment_manifest: AttachmentManifestSink = AttachmentManifestSink::new();
+        let mut attachment_folder: AttachmentFolderSink = AttachmentFolderSink::new();
+
+        let mut export_sinks: Vec<Box<dyn MessageSink + Send>> = self.options.export_formats.iter()
+            .map(|format: &ExportFormat| export_sink(*format))
+            .collect::<Result<_, _>>()?;
+
+        let mut message_sinks: Vec<&mut dyn MessageSink> = vec![&mut messages_csv, &mut attachment_manifest];
+        message_sinks.extend(export_sinks.iter_mut().map(|sink| sink.as_mut() as &mut dyn MessageSink));
+        message_sinks.extend(self.message_sinks.iter_mut().map(|sink| sink.as_mut() as &mut dyn MessageSink));
+
+        let mut attachment_sinks: Vec<&mut dyn AttachmentSink> = Vec::new();
+        if self.options.export_attachments {
+            attachment_sinks.push(&mut attachment_folder);
+            attachment_sinks.extend(self.attachment_sinks.iter_mut().map(|sink| sink.as_mut() as &mut dyn AttachmentSink));
+        }
+
+        let context: SinkContext = SinkContext {
+            mbox_path: mbox_path.as_ref(),
+            output_path: output_path.as_ref(),
+            options: &self.options,
+            diagnostics: &self.diagnostics,
+        };
+        extract_mbox(&context, &mut message_sinks, &mut attachment_sinks, &self.diagnostics, progress_callback)
+    }
+}
+
+/// Parses an .mbox file, extracts its messages and attachments, and drives the sinks with the results.
+///
+/// # Arguments
+///
+/// * `context` - The .mbox file, output folder and options of the run.
+/// * `message_sinks` - The sinks receiving every message and attachment record.
+/// * `attachment_sinks` - The sinks receiving the content of every attachment that passes the attachment filter.
+/// * `diagnostics` - The collector receiving every problem encountered; also written to `diagnostics.json`.
+/// * `progress_callback` - A closure that will be called with the overall progress, from 0.0 to 1.0.
+///
+/// # Returns
+///
+/// A Result containing the summary of the run.
+///
+/// # Errors
+///
+/// This function will return an error if the .mbox file cannot be read, a message sink fails,
+/// or an attachment sink fails while `continue_on_error` is disabled.
+fn extract_mbox<F>(
+    context: &SinkContext,
+    message_sinks: &mut [&mut dyn MessageSink],
+    attachment_sinks: &mut [&mut dyn AttachmentSink],
+    diagnostics: &Diagnostics,
+    mut progress_callback: F,
+) -> Result<ExtractionSummary, ExtractorError>
+where
+    F: FnMut(f32)
+{
+    let options: &ExtractionOptions = context.options;
+    let mut summary: ExtractionSummary = ExtractionSummary::default();
+
+    // Step 1: Read MBOX file
+    progress_callback(0.0);
+    let mbox_content: String = read_mbox_file(context.mbox_path)?;
+    progress_callback(FILE_READ_WEIGHT);
+
+    // Step 2: Parse MBOX entries
+    let mbox_entries: Vec<MboxEntry> = split_mbox_entries(&mbox_content, diagnostics);
+    progress_callback(FILE_READ_WEIGHT + PARSING_WEIGHT);
+
+    for sink in message_sinks.iter_mut() {
+        sink.begin(context)?;
+    }
+    for sink in attachment_sinks.iter_mut() {
+        sink.begin(context)?;
+    }
+
+    // Step 3: Process entries
+    let total_entries: usize = mbox_entries.len();
+
+    for (index, entry) in mbox_entries.iter().enumerate() {
+        for sink in message_sinks.iter_mut() {
+            sink.entry(entry)?;
+            for message in &entry.messages {
+                sink.message(entry, message)?;
+            }
+        }
+        summary.messages += entry.messages.len();
+
+        for (attachment_index, attachment) in entry.attachments.iter().enumerate() {
+            let processed = process_attachment(entry, attachment_index, attachment, attachment_sinks, options);
+            let record: AttachmentRecord = match processed {
+                Ok((record, None)) => {
+                    if options.export_attachments {
+                        if !record.filter_reason.is_empty() {
+                            summary.attachments_filtered += 1;
+                        } else if record.duplicate {
+                            summary.attachments_deduplicated += 1;
+                        } else if !record.stored_path.is_empty() {
+                            summary.attachments_written += 1;
+                        }
+                    }
+                    record
+                }
+                // A sink failed after the ones before it handled the attachment, which may already be stored,
+                // so the record is kept to stay in the manifest and every other output
+                Ok((record, Some(e))) if options.continue_on_error => {
+                    if record.duplicate {
+                        summary.attachments_deduplicated += 1;
+                    } else if !record.stored_path.is_empty() {
+                        summary.attachments_written += 1;
+                    } else {
+                        summary.attachments_failed += 1;
+                    }
+                    diagnostics.record(
+                        DiagnosticKind::AttachmentWriteFailed,
+                        entry.index,
+                        entry.byte_offset,
+                        format!("'{}': {}", attachment.filename, e),
+                    );
+                    record
+                }
+                Ok((_, Some(e))) => return Err(e),
+                Err(e) if options.continue_on_error || !options.export_attachments => {
+                    let kind: DiagnosticKind = if options.export_attachments {
+                        summary.attachments_failed += 1;
+                        DiagnosticKind::AttachmentWriteFailed
+                    } else {
+                        DiagnosticKind::BadAttachment
+                    };
+                    diagnostics.record(kind, entry.index, entry.byte_offset, format!("'{}': {}", attachment.filename, e));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            for sink in message_sinks.iter_mut() {
+                sink.attachment(entry, &record)?;
+            }
+        }
+
+        for sink in message_sinks.iter_mut() {
+            sink.end_entry(entry)?;
+        }
+
+        let progress: f32 = FILE_READ_WEIGHT + PARSING_WEIGHT + PROCESSING_WEIGHT * (index + 1) as f32 / total_entries as f32;
+        progress_callback(progress);
+    }
+
+    // Step 4: Finish the sinks
+    let total_sinks: usize = message_sinks.len() + attachment_sinks.len();
+    let mut finished_sinks: usize = 0;
+    for sink in attachment_sinks.iter_mut() {
+        sink.finish()?;
+        finished_sinks += 1;
+        progress_callback(FILE_READ_WEIGHT + PARSING_WEIGHT + PROCESSING_WEIGHT + WRITING_WEIGHT * finished_sinks as f32 / total_sinks as f32);
+    }
+    for sink in message_sinks.iter_mut() {
+        sink.finish_with_progress(&mut |sink_progress: f32| {
+            let finished: f32 = finished_sinks as f32 + sink_progress.clamp(0.0, 1.0);
+            progress_callback(FILE_READ_WEIGHT + PARSING_WEIGHT + PROCESSING_WEIGHT + WRITING_WEIGHT * finished / total_sinks as f32);
+        })?;
+        finished_sinks += 1;
+        progress_callback(FILE_READ_WEIGHT + PARSING_WEIGHT + PROCESSING_WEIGHT + WRITING_WEIGHT * finished_sinks as f32 / total_sinks as f32);
+    }
+
+    // Step 5: Write diagnostics
+    let diagnostics_path: PathBuf = context.output_path.join("diagnostics.json");
+    write_diagnostics_to_json(diagnostics, path_to_str(&diagnostics_path)?)?;
+
+    summary.entries = total_entries;
+    Ok(summary)
+}
+
+/// Decodes and inspects a single attachment for the manifest and, when it passes the attachment filter,
+/// hands its content to the attachment sinks, which fill in where it was stored.
+///
+/// Fails if the attachment cannot be decoded. When an attachment sink fails, the sinks after it are skipped and
+/// the record, as filled in by the sinks before it, is returned along with the error.
+fn process_attachment(
+    entry: &MboxEntry,
+    attachment_index: usize,
+    attachment: &Attachment,
+    attachment_sinks: &mut [&mut dyn AttachmentSink],
+    options: &ExtractionOptions,
+) -> Result<(AttachmentRecord, Option<ExtractorError>), ExtractorError> {
+    let content: Vec<u8> = decode_attachment(attachment)?;
+    let message: Option<&Message> = entry.owning_message(attachment);
+
+    let declared_content_type: String = attachment.mime_type();
+    let sniffed_content_type: String = sniff_content_type(&content).unwrap_or_default();
+    let type_mismatch: bool = !sniffed_content_type.is_empty()
+        && normalize_mime_type(&sniffed_content_type) != normalize_mime_type(&declared_content_type);
+
+    let mut record: AttachmentRecord = AttachmentRecord {
+        message_id: message.map(|m: &Message| m.message_id.clone()).unwrap_or_default(),
+        entry_index: entry.index,
+        attachment_index,
+        original_filename: attachment.filename.clone(),
+        stored_path: String::new(),
+        declared_content_type,
+        sniffed_content_type,
+        size: content.len(),
+        sha256: sha256_hex(&content),
+        type_mismatch,
+        duplicate: false,
+        filter_reason: String::new(),
+    };
+
+    if let Some(reason) = options.attachment_filter.rejection_reason(
+        &attachment.filename,
+        &record.declared_content_type,
+        &record.sniffed_content_type,
+        record.size,
+    ) {
+        record.filter_reason = reason;
+        return Ok((record, None));
+    }
+
+    for sink in attachment_sinks.iter_mut() {
+        if let Err(e) = sink.attachment(entry, message, attachment, &content, &mut record) {
+            return Ok((record, Some(e)));
+        }
+    }
+
+    Ok((record, None))
+}
+
+/// Helper function to map MIME type aliases onto a single spelling before comparing them
+fn normalize_mime_type(mime_type: &str) -> &str {
+    match mime_type {
+        "image/jpg" | "image/pjpeg" => "image/jpeg",
+        "application/x-zip-compressed" => "application/zip",
+        "audio/mp3" => "audio/mpeg",
+        other => other,
+    }
+}
@@ -0,0 +1,28 @@
+//! Extracts messages and attachments from Google Chat .mbox exports.
+//!
+//! The [`Extractor`] runs the whole pipeline (parsing, attachment export, CSV/JSON outputs and diagnostics),
+//! while [`split_mbox_entries`] and the [`models`] give direct access to the parsed data.
+//! [`split_mbox`] writes filtered or split copies of an .mbox file, leaving its entries untouched.
+//! Additional outputs are added by implementing the traits in [`sinks`].
+//! The GUI and command-line front ends live behind the `gui` and `cli` cargo features,
+//! so library users can depend on this crate with `default-features = false`.
+
+pub mod error;
+pub mod models;
+pub mod sinks;
+mod extractor;
+mod parsers;
+mod splitter;
+mod utils;
+
+pub use error::ExtractorError;
+pub use extractor::Extractor;
+pub use models::{
+    Attachment, AttachmentFilter, AttachmentRecord, CsvFormat, Diagnostic, DiagnosticKind, Diagnostics, DocumentUnit,
+    ExportFormat, ExtractionOptions, ExtractionSummary, LineEnding, LoadFileFormat, MboxEntry, MboxSplitOptions,
+    MboxSplitSummary, Message, MessageColumn, PdfFormat, QuoteStyle, RawMessage, SlackFormat, SplitBy,
+    TranscriptFormat, DEFAULT_TRANSCRIPT_TIMESTAMP_FORMAT, EXCEL_MAX_ROWS,
+};
+pub use parsers::split_mbox_entries;
+pub use splitter::split_mbox;
+pub use utils::DEFAULT_ATTACHMENT_TEMPLATE;
@@ -1,11 +1,38 @@
-mod models;
-mod parsers;
-mod utils;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "gui")]
 mod ui;
 
-use ui::run_ui;
+use std::process::ExitCode;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    run_ui()?;
-    Ok(())
-}
\ No newline at end of file
+fn main() -> ExitCode {
+    // Any argument selects the command-line interface, so `--help` and `--version` work too
+    #[cfg(all(feature = "gui", feature = "cli"))]
+    if std::env::args_os().len() <= 1 {
+        return run_ui();
+    }
+
+    #[cfg(feature = "cli")]
+    return cli::run();
+
+    #[cfg(all(feature = "gui", not(feature = "cli")))]
+    return run_ui();
+
+    #[cfg(not(any(feature = "gui", feature = "cli")))]
+    {
+        eprintln!("Error: built without the `gui` and `cli` features; use the library API instead");
+        ExitCode::FAILURE
+    }
+}
+
+/// Runs the GUI until its window is closed
+#[cfg(feature = "gui")]
+fn run_ui() -> ExitCode {
+    match ui::run_ui() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,13 +1,58 @@
+use serde::Serialize;
+
 /// Represents an attachment.
 ///
 /// # Fields
 ///
 /// * `content_type` - A String containing the MIME type of the attachment.
 /// * `filename` - A String representing the name of the attachment file.
-/// * `content` - A String containing the base64-encoded content of the attachment.
-#[derive(Debug)]
+/// * `content` - A String containing the base64-encoded content of the attachment; not serialized, as the
+///   decoded content is written to the `attachments` folder instead.
+#[derive(Debug, Serialize)]
 pub struct Attachment {
     pub content_type: String,
     pub filename: String,
+    #[serde(skip_serializing)]
     pub content: String,
-}
\ No newline at end of file
+}
+
+impl Attachment {
+    /// Returns the declared MIME type without parameters, lowercased (e.g. `image/png` for `image/PNG; name=a.png`).
+    pub fn mime_type(&self) -> String {
+        self.content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+    }
+}
+
+/// Represents the manifest record of an attachment, whether or not it was written to disk.
+///
+/// # Fields
+///
+/// * `message_id` - The id of the message owning the attachment (see `MboxEntry::owning_message`).
+/// * `entry_index` - The index of the .mbox entry the attachment belongs to.
+/// * `attachment_index` - The position of the attachment in `MboxEntry::attachments`, pairing the record with it.
+/// * `original_filename` - The filename as declared in the .mbox file.
+/// * `stored_path` - The path of the written file relative to the output folder, or an empty String if it was not written.
+/// * `declared_content_type` - The MIME type declared in the .mbox file, without parameters.
+/// * `sniffed_content_type` - The MIME type detected from the content's magic bytes, or an empty String if unknown.
+/// * `size` - The size of the decoded content in bytes.
+/// * `sha256` - The hexadecimal SHA-256 digest of the decoded content.
+/// * `type_mismatch` - Whether the declared and sniffed MIME types disagree.
+/// * `duplicate` - Whether identical content had already been stored, so no new file was written.
+/// * `filter_reason` - Why the attachment filter left the attachment out, or an empty String if it passed.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct AttachmentRecord {
+    pub message_id: String,
+    pub entry_index: usize,
+    #[serde(skip)]
+    pub attachment_index: usize,
+    pub original_filename: String,
+    pub stored_path: String,
+    pub declared_content_type: String,
+    pub sniffed_content_type: String,
+    pub size: usize,
+    pub sha256: String,
+    pub type_mismatch: bool,
+    pub duplicate: bool,
+    pub filter_reason: String,
+}
@@ -0,0 +1,211 @@
+use glob::{MatchOptions, Pattern};
+
+/// Represents the rules deciding which attachments are written to disk.
+///
+/// Every list is optional: an empty allow list allows everything, an empty deny list denies nothing.
+/// MIME types may end in `/*` (e.g. `video/*`), extensions are matched without the leading dot
+/// and name patterns are case-insensitive globs (e.g. `invoice_*.pdf`).
+///
+/// # Fields
+///
+/// * `allowed_mime_types` - MIME types of which the sniffed type must match at least one; the declared type is
+///   only checked when the content has no known signature.
+/// * `denied_mime_types` - MIME types of which neither the declared nor the sniffed type may match any.
+/// * `allowed_extensions` - Filename extensions of which the attachment must have one.
+/// * `denied_extensions` - Filename extensions the attachment must not have.
+/// * `max_size` - The maximum decoded size in bytes; larger attachments are recorded but not written.
+/// * `include_patterns` - Compiled filename glob patterns of which the attachment must match at least one.
+/// * `exclude_patterns` - Compiled filename glob patterns the attachment must not match.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct AttachmentFilter {
+    pub allowed_mime_types: Vec<String>,
+    pub denied_mime_types: Vec<String>,
+    pub allowed_extensions: Vec<String>,
+    pub denied_extensions: Vec<String>,
+    pub max_size: Option<usize>,
+    pub include_patterns: Vec<Pattern>,
+    pub exclude_patterns: Vec<Pattern>,
+}
+
+impl AttachmentFilter {
+    /// Sets the MIME types of which the attachment must match at least one.
+    pub fn allowed_mime_types(mut self, allowed_mime_types: Vec<String>) -> Self {
+        self.allowed_mime_types = allowed_mime_types;
+        self
+    }
+
+    /// Sets the MIME types the attachment must not match.
+    pub fn denied_mime_types(mut self, denied_mime_types: Vec<String>) -> Self {
+        self.denied_mime_types = denied_mime_types;
+        self
+    }
+
+    /// Sets the filename extensions of which the attachment must have one.
+    pub fn allowed_extensions(mut self, allowed_extensions: Vec<String>) -> Self {
+        self.allowed_extensions = allowed_extensions;
+        self
+    }
+
+    /// Sets the filename extensions the attachment must not have.
+    pub fn denied_extensions(mut self, denied_extensions: Vec<String>) -> Self {
+        self.denied_extensions = denied_extensions;
+        self
+    }
+
+    /// Sets the maximum decoded size in bytes.
+    pub fn max_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Compiles and sets the filename glob patterns of which the attachment must match at least one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if a pattern is not a valid glob.
+    pub fn include_patterns(mut self, include_patterns: &[String]) -> Result<Self, String> {
+        self.include_patterns = compile_patterns(include_patterns)?;
+        Ok(self)
+    }
+
+    /// Compiles and sets the filename glob patterns the attachment must not match.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if a pattern is not a valid glob.
+    pub fn exclude_patterns(mut self, exclude_patterns: &[String]) -> Result<Self, String> {
+        self.exclude_patterns = compile_patterns(exclude_patterns)?;
+        Ok(self)
+    }
+
+    /// Splits a comma-separated list, as typed into the GUI or passed on the command line, into its trimmed, non-empty items.
+    pub fn parse_list(list: &str) -> Vec<String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|item: &&str| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Checks an attachment against the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - The filename of the attachment.
+    /// * `declared_content_type` - The MIME type declared in the .mbox file.
+    /// * `sniffed_content_type` - The MIME type detected from the content, or an empty string if unknown.
+    /// * `size` - The decoded size of the attachment in bytes.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the attachment passes the filter, or `Some` reason describing why it was filtered out.
+    pub fn rejection_reason(&self, filename: &str, declared_content_type: &str, sniffed_content_type: &str, size: usize) -> Option<String> {
+        let mime_types: Vec<&str> = [declared_content_type, sniffed_content_type].into_iter()
+            .filter(|mime_type: &&str| !mime_type.is_empty())
+            .collect();
+        // The content decides whether a type is allowed; the declared type can claim anything
+        let checked_mime_type: &str = if sniffed_content_type.is_empty() { declared_content_type } else { sniffed_content_type };
+        let extension: String = filename.rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        if let Some(max_size) = self.max_size.filter(|&max_size| size > max_size) {
+            return Some(format!("larger than {} bytes", max_size));
+        }
+        if let Some(denied) = self.denied_mime_types.iter().find(|p| mime_types.iter().any(|m| mime_matches(p, m))) {
+            return Some(format!("MIME type denied by '{}'", denied));
+        }
+        if !self.allowed_mime_types.is_empty() && !self.allowed_mime_types.iter().any(|p| mime_matches(p, checked_mime_type)) {
+            return Some(format!("MIME type '{}' not allowed", checked_mime_type));
+        }
+        if self.denied_extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&extension)) {
+            return Some(format!("extension '{}' denied", extension));
+        }
+        if !self.allowed_extensions.is_empty() && !self.allowed_extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&extension)) {
+            return Some(format!("extension '{}' not allowed", extension));
+        }
+        if let Some(excluded) = self.exclude_patterns.iter().find(|p| glob_matches(p, filename)) {
+            return Some(format!("name excluded by '{}'", excluded.as_str()));
+        }
+        if !self.include_patterns.is_empty() && !self.include_patterns.iter().any(|p| glob_matches(p, filename)) {
+            return Some("name not included by any pattern".to_string());
+        }
+
+        None
+    }
+}
+
+/// Matches a MIME type against a pattern such as `image/png` or `image/*`.
+fn mime_matches(pattern: &str, mime_type: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime_type.split('/').next().is_some_and(|top: &str| top.eq_ignore_ascii_case(prefix)),
+        None => pattern == "*" || pattern.eq_ignore_ascii_case(mime_type),
+    }
+}
+
+/// Compiles filename glob patterns, naming the first invalid one.
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+    patterns.iter()
+        .map(|pattern: &String| Pattern::new(pattern)
+            .map_err(|e| format!("invalid attachment name pattern '{}': {}", pattern, e)))
+        .collect()
+}
+
+/// Matches a filename against a case-insensitive glob pattern.
+fn glob_matches(pattern: &Pattern, filename: &str) -> bool {
+    let options: MatchOptions = MatchOptions {
+        case_sensitive: false,
+        ..MatchOptions::new()
+    };
+    pattern.matches_with(filename, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern: &&str| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn allow_list_checks_the_sniffed_type() {
+        let filter: AttachmentFilter = AttachmentFilter::default().allowed_mime_types(patterns(&["image/*"]));
+        assert!(filter.rejection_reason("a.png", "image/png", "image/png", 1).is_none());
+        assert!(filter.rejection_reason("a.png", "image/png", "application/x-executable", 1).is_some());
+        assert!(filter.rejection_reason("a.png", "application/octet-stream", "image/png", 1).is_none());
+    }
+
+    #[test]
+    fn allow_list_falls_back_to_the_declared_type() {
+        let filter: AttachmentFilter = AttachmentFilter::default().allowed_mime_types(patterns(&["text/*"]));
+        assert!(filter.rejection_reason("a.txt", "text/plain", "", 1).is_none());
+        assert!(filter.rejection_reason("a.bin", "application/octet-stream", "", 1).is_some());
+    }
+
+    #[test]
+    fn deny_list_checks_both_types() {
+        let filter: AttachmentFilter = AttachmentFilter::default().denied_mime_types(patterns(&["application/x-executable"]));
+        assert!(filter.rejection_reason("a.png", "image/png", "application/x-executable", 1).is_some());
+        assert!(filter.rejection_reason("a", "application/x-executable", "", 1).is_some());
+        assert!(filter.rejection_reason("a.png", "image/png", "image/png", 1).is_none());
+    }
+
+    #[test]
+    fn name_patterns_are_case_insensitive() {
+        let filter: AttachmentFilter = AttachmentFilter::default()
+            .include_patterns(&patterns(&["invoice_*.pdf"])).unwrap()
+            .exclude_patterns(&patterns(&["*draft*"])).unwrap();
+        assert!(filter.rejection_reason("Invoice_12.PDF", "", "", 1).is_none());
+        assert!(filter.rejection_reason("invoice_12_draft.pdf", "", "", 1).is_some());
+        assert!(filter.rejection_reason("receipt.pdf", "", "", 1).is_some());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let error: String = AttachmentFilter::default().include_patterns(&patterns(&["[invoice*.pdf"])).unwrap_err();
+        assert!(error.contains("[invoice*.pdf"));
+        assert!(AttachmentFilter::default().exclude_patterns(&patterns(&["ok*", "***"])).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+use std::str::FromStr;
+use super::Message;
+
+/// Represents the line terminator written after every CSV record.
+///
+/// # Variants
+///
+/// * `CrLf` - `\r\n`, as required by RFC 4180.
+/// * `Lf` - `\n`, as usual on Unix systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    CrLf,
+    Lf,
+}
+
+impl LineEnding {
+    /// Returns the characters terminating a record.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Lf => "\n",
+        }
+    }
+}
+
+impl FromStr for LineEnding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "crlf" => Ok(LineEnding::CrLf),
+            "lf" => Ok(LineEnding::Lf),
+            other => Err(format!("unknown line ending '{}' (expected 'crlf' or 'lf')", other)),
+        }
+    }
+}
+
+/// Represents which CSV fields are enclosed in quotes.
+///
+/// # Variants
+///
+/// * `Always` - Every field is quoted.
+/// * `Necessary` - Only fields containing the delimiter, the quote character or a line break are quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    Always,
+    Necessary,
+}
+
+impl FromStr for QuoteStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "always" => Ok(QuoteStyle::Always),
+            "necessary" => Ok(QuoteStyle::Necessary),
+            other => Err(format!("unknown quote style '{}' (expected 'always' or 'necessary')", other)),
+        }
+    }
+}
+
+/// Represents a column of `messages.csv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageColumn {
+    MessageId,
+    Sender,
+    Timestamp,
+    Content,
+}
+
+impl MessageColumn {
+    /// Every column, in the default order.
+    pub const ALL: [MessageColumn; 4] = [
+        MessageColumn::MessageId,
+        MessageColumn::Sender,
+        MessageColumn::Timestamp,
+        MessageColumn::Content,
+    ];
+
+    /// Returns the name of the column, as written in the header row.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessageColumn::MessageId => "message_id",
+            MessageColumn::Sender => "sender",
+            MessageColumn::Timestamp => "timestamp",
+            MessageColumn::Content => "content",
+        }
+    }
+
+    /// Returns the value of the column for a message.
+    pub fn value<'a>(&self, message: &'a Message) -> &'a str {
+        match self {
+            MessageColumn::MessageId => &message.message_id,
+            MessageColumn::Sender => &message.sender,
+            MessageColumn::Timestamp => &message.timestamp,
+            MessageColumn::Content => &message.content,
+        }
+    }
+}
+
+impl fmt::Display for MessageColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MessageColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s.trim().to_ascii_lowercase();
+        MessageColumn::ALL.into_iter()
+            .find(|column: &MessageColumn| column.name() == name)
+            .ok_or_else(|| format!(
+                "unknown column '{}' (expected one of {})",
+                name,
+                MessageColumn::ALL.map(|column: MessageColumn| column.name()).join(", ")
+            ))
+    }
+}
+
+/// The maximum number of rows, header included, that Excel can open from a single sheet.
+pub const EXCEL_MAX_ROWS: usize = 1_048_576;
+
+/// Represents the dialect and layout of the CSV files written by the extractor.
+///
+/// The dialect applies to `messages.csv` and `attachments.csv`; the columns only to `messages.csv`.
+///
+/// # Fields
+///
+/// * `delimiter` - The character separating fields, e.g. `,`, `;` or a tab.
+/// * `quote` - The character enclosing quoted fields; occurrences inside a field are doubled.
+/// * `line_ending` - The line terminator written after every record.
+/// * `quote_style` - Which fields are enclosed in quotes.
+/// * `byte_order_mark` - Whether files start with a UTF-8 byte order mark, which Excel needs to detect UTF-8.
+/// * `message_columns` - The columns of `messages.csv`, in order.
+/// * `max_rows_per_file` - The maximum number of rows, header included, of a messages file; longer outputs are rolled
+///   into numbered files (`messages_1.csv`, `messages_2.csv`, ...). Use `EXCEL_MAX_ROWS` to stay within Excel's limit.
+/// * `neutralize_formulas` - Whether cells starting with `=`, `+`, `-`, `@`, a tab or a carriage return are prefixed with
+///   `'`, so spreadsheet applications display them instead of evaluating them as formulas.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct CsvFormat {
+    pub delimiter: char,
+    pub quote: char,
+    pub line_ending: LineEnding,
+    pub quote_style: QuoteStyle,
+    pub byte_order_mark: bool,
+    pub message_columns: Vec<MessageColumn>,
+    pub max_rows_per_file: Option<usize>,
+    pub neutralize_formulas: bool,
+}
+
+impl CsvFormat {
+    /// Sets the character separating fields.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the character enclosing quoted fields.
+    pub fn quote(mut self, quote: char) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Sets the line terminator written after every record.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Sets which fields are enclosed in quotes.
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Sets whether files start with a UTF-8 byte order mark.
+    pub fn byte_order_mark(mut self, byte_order_mark: bool) -> Self {
+        self.byte_order_mark = byte_order_mark;
+        self
+    }
+
+    /// Sets the columns of `messages.csv`, in order.
+    pub fn message_columns(mut self, message_columns: Vec<MessageColumn>) -> Self {
+        self.message_columns = message_columns;
+        self
+    }
+
+    /// Sets the maximum number of rows, header included, of a messages file.
+    pub fn max_rows_per_file(mut self, max_rows_per_file: Option<usize>) -> Self {
+        self.max_rows_per_file = max_rows_per_file;
+        self
+    }
+
+    /// Sets whether formula-leading cells are prefixed with `'`.
+    pub fn neutralize_formulas(mut self, neutralize_formulas: bool) -> Self {
+        self.neutralize_formulas = neutralize_formulas;
+        self
+    }
+
+    /// Checks that the format can produce unambiguous CSV.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the delimiter and quote are equal or line breaks,
+    /// if no message column is selected, or if the row limit leaves no room for messages.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.delimiter == self.quote {
+            return Err(format!("the CSV delimiter and quote must differ (both are '{}')", self.delimiter));
+        }
+        if ['\r', '\n'].contains(&self.delimiter) || ['\r', '\n'].contains(&self.quote) {
+            return Err("the CSV delimiter and quote cannot be line breaks".to_string());
+        }
+        if self.message_columns.is_empty() {
+            return Err("at least one message column must be selected".to_string());
+        }
+        if self.max_rows_per_file.is_some_and(|max_rows: usize| max_rows < 2) {
+            return Err("the CSV row limit must leave room for the header and at least one message".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            line_ending: LineEnding::CrLf,
+            quote_style: QuoteStyle::Always,
+            byte_order_mark: false,
+            message_columns: MessageColumn::ALL.to_vec(),
+            max_rows_per_file: None,
+            neutralize_formulas: false,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+
+/// Represents the kind of problem recorded in a diagnostic.
+///
+/// # Variants
+///
+/// * `SkippedEntry` - An entire .mbox entry was dropped.
+/// * `UnparsedMessage` - A message inside an entry could not be parsed and was dropped.
+/// * `FailedTimestamp` - A message timestamp could not be converted to RFC 3339 and was kept verbatim.
+/// * `BadAttachment` - An attachment part was malformed and was dropped.
+/// * `AttachmentWriteFailed` - An attachment could not be decoded or written to disk and was skipped.
+/// * `FailedModifiedTime` - The modification time of a stored attachment could not be set; the file was kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    SkippedEntry,
+    UnparsedMessage,
+    FailedTimestamp,
+    BadAttachment,
+    AttachmentWriteFailed,
+    FailedModifiedTime,
+}
+
+impl DiagnosticKind {
+    /// Returns whether this kind of problem means data was left out of the outputs.
+    pub fn is_data_loss(&self) -> bool {
+        !matches!(self, DiagnosticKind::FailedTimestamp | DiagnosticKind::FailedModifiedTime)
+    }
+
+    /// Returns a short human-readable label used in run summaries.
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiagnosticKind::SkippedEntry => "skipped entries",
+            DiagnosticKind::UnparsedMessage => "unparsed messages",
+            DiagnosticKind::FailedTimestamp => "failed timestamps",
+            DiagnosticKind::BadAttachment => "bad attachments",
+            DiagnosticKind::AttachmentWriteFailed => "failed attachment writes",
+            DiagnosticKind::FailedModifiedTime => "failed modification times",
+        }
+    }
+}
+
+/// Represents a single problem encountered while extracting an .mbox file.
+///
+/// # Fields
+///
+/// * `kind` - The kind of problem that was encountered.
+/// * `entry_index` - The zero-based index of the .mbox entry the problem belongs to.
+/// * `byte_offset` - The byte offset of that entry within the .mbox file.
+/// * `detail` - A String describing what went wrong.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub entry_index: usize,
+    pub byte_offset: usize,
+    pub detail: String,
+}
+
+/// Collects diagnostics from the (parallel) extraction pipeline.
+///
+/// Clones share their records, so a sink can keep a clone of the run's collector to record problems.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    records: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a new diagnostic for the entry at `entry_index`/`byte_offset`.
+    pub fn record(&self, kind: DiagnosticKind, entry_index: usize, byte_offset: usize, detail: impl Into<String>) {
+        let diagnostic: Diagnostic = Diagnostic {
+            kind,
+            entry_index,
+            byte_offset,
+            detail: detail.into(),
+        };
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).push(diagnostic);
+    }
+
+    /// Removes all recorded diagnostics.
+    pub fn clear(&self) {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Returns all recorded diagnostics ordered by their position in the .mbox file.
+    pub fn sorted(&self) -> Vec<Diagnostic> {
+        let mut records: Vec<Diagnostic> = self.records.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        records.sort_by_key(|d: &Diagnostic| (d.byte_offset, d.kind));
+        records
+    }
+
+    /// Returns the number of recorded diagnostics per kind.
+    pub fn counts(&self) -> BTreeMap<DiagnosticKind, usize> {
+        let mut counts: BTreeMap<DiagnosticKind, usize> = BTreeMap::new();
+        for diagnostic in self.records.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            *counts.entry(diagnostic.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Builds a one-line summary such as "2 skipped entries, 5 failed timestamps".
+    pub fn summary(&self) -> String {
+        let counts: BTreeMap<DiagnosticKind, usize> = self.counts();
+        if counts.is_empty() {
+            return "no problems found".to_string();
+        }
+        counts.iter()
+            .map(|(kind, count)| format!("{} {}", count, kind.label()))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Represents an additional output written next to `messages.csv`.
+///
+/// Some formats rely on optional dependencies and are only available when the matching
+/// cargo feature is enabled (see `is_available`).
+///
+/// # Variants
+///
+/// * `Xlsx` - An Excel workbook, `messages.xlsx` (`xlsx` feature).
+/// * `Json` - A single JSON document nesting entries, messages and attachments, `messages.json`.
+/// * `Ndjson` - One JSON document per message, `messages.ndjson`.
+/// * `Sqlite` - A SQLite database with normalized tables and a full-text index, `messages.sqlite` (`sqlite` feature).
+/// * `Parquet` - Apache Parquet files with typed columns, `messages.parquet` and `attachments.parquet` (`parquet` feature).
+/// * `Arrow` - The same tables as Arrow IPC files, `messages.arrow` and `attachments.arrow` (`parquet` feature).
+/// * `Html` - A standalone HTML transcript per conversation with an index page, in the `html` folder.
+/// * `Markdown` - A Markdown transcript per conversation (or per conversation and month), in the `markdown` folder.
+/// * `Text` - A plain-text `[timestamp] sender: message` transcript per conversation, in the `text` folder.
+/// * `Pdf` - A paginated, Bates-numbered PDF transcript per conversation, in the `pdf` folder (`pdf` feature).
+/// * `LoadFile` - Concordance `.dat`/`.opt` and EDRM XML load files for review platforms, in the `loadfile` folder.
+/// * `Eml` - An `.eml` file per .mbox entry with its original headers and attachments, in `eml/<conversation>`.
+/// * `Mbox` - The same messages as an `.mbox` file per conversation, in the `mbox` folder.
+/// * `Slack` - Slack's export layout, with a channel per conversation and a JSON file per day, in the `slack` folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExportFormat {
+    Xlsx,
+    Json,
+    Ndjson,
+    Sqlite,
+    Parquet,
+    Arrow,
+    Html,
+    Markdown,
+    Text,
+    Pdf,
+    LoadFile,
+    Eml,
+    Mbox,
+    Slack,
+}
+
+impl ExportFormat {
+    /// Every format, in the order they are listed in the interfaces.
+    pub const ALL: [ExportFormat; 14] = [
+        ExportFormat::Xlsx,
+        ExportFormat::Json,
+        ExportFormat::Ndjson,
+        ExportFormat::Sqlite,
+        ExportFormat::Parquet,
+        ExportFormat::Arrow,
+        ExportFormat::Html,
+        ExportFormat::Markdown,
+        ExportFormat::Text,
+        ExportFormat::Pdf,
+        ExportFormat::LoadFile,
+        ExportFormat::Eml,
+        ExportFormat::Mbox,
+        ExportFormat::Slack,
+    ];
+
+    /// Returns the name of the format, as accepted on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Sqlite => "sqlite",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Arrow => "arrow",
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "markdown",
+            ExportFormat::Text => "text",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::LoadFile => "loadfile",
+            ExportFormat::Eml => "eml",
+            ExportFormat::Mbox => "mbox",
+            ExportFormat::Slack => "slack",
+        }
+    }
+
+    /// Returns a short human-readable label used in the GUI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "Excel Workbook (.xlsx)",
+            ExportFormat::Json => "JSON Document (.json)",
+            ExportFormat::Ndjson => "JSON Lines (.ndjson)",
+            ExportFormat::Sqlite => "SQLite Database (.sqlite)",
+            ExportFormat::Parquet => "Apache Parquet (.parquet)",
+            ExportFormat::Arrow => "Arrow IPC (.arrow)",
+            ExportFormat::Html => "HTML Transcripts (html/)",
+            ExportFormat::Markdown => "Markdown Transcripts (markdown/)",
+            ExportFormat::Text => "Plain-Text Transcripts (text/)",
+            ExportFormat::Pdf => "PDF Transcripts (pdf/)",
+            ExportFormat::LoadFile => "E-Discovery Load Files (loadfile/)",
+            ExportFormat::Eml => "Mail Files per Conversation (eml/)",
+            ExportFormat::Mbox => "Mailbox per Conversation (mbox/)",
+            ExportFormat::Slack => "Slack Export (slack/)",
+        }
+    }
+
+    /// Returns whether this build includes the cargo feature the format needs.
+    pub fn is_available(&self) -> bool {
+        match self {
+            ExportFormat::Xlsx => cfg!(feature = "xlsx"),
+            ExportFormat::Json | ExportFormat::Ndjson | ExportFormat::Html
+            | ExportFormat::Markdown | ExportFormat::Text | ExportFormat::LoadFile | ExportFormat::Eml
+            | ExportFormat::Mbox | ExportFormat::Slack => true,
+            ExportFormat::Sqlite => cfg!(feature = "sqlite"),
+            ExportFormat::Parquet | ExportFormat::Arrow => cfg!(feature = "parquet"),
+            ExportFormat::Pdf => cfg!(feature = "pdf"),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s.trim().to_ascii_lowercase();
+        ExportFormat::ALL.into_iter()
+            .find(|format: &ExportFormat| format.name() == name)
+            .ok_or_else(|| format!(
+                "unknown format '{}' (expected one of {})",
+                name,
+                ExportFormat::ALL.map(|format: ExportFormat| format.name()).join(", ")
+            ))
+    }
+}
@@ -0,0 +1,163 @@
+use serde::Serialize;
+use crate::utils::DEFAULT_ATTACHMENT_TEMPLATE;
+use super::{AttachmentFilter, CsvFormat, Diagnostics, ExportFormat, LoadFileFormat, PdfFormat, SlackFormat, TranscriptFormat};
+
+/// Represents the options controlling a single extraction run.
+///
+/// # Fields
+///
+/// * `export_attachments` - Whether attachments should be written to the `attachments` folder.
+/// * `continue_on_error` - Whether a failed attachment write is recorded and skipped instead of aborting the run.
+/// * `deduplicate_attachments` - Whether attachments are stored once per SHA-256 digest instead of once per occurrence.
+/// * `attachment_path_template` - The layout of the `attachments` folder (see `render_attachment_path`);
+///   ignored when deduplicating, as content-addressed files are always stored flat.
+/// * `attachment_filter` - The rules deciding which attachments are written; filtered ones are still listed in the manifest.
+/// * `csv_format` - The dialect of `messages.csv` and `attachments.csv`, and the columns of `messages.csv`.
+/// * `export_formats` - The additional outputs written next to `messages.csv`.
+/// * `transcript_format` - The layout of the transcript exports.
+/// * `pdf_format` - The Bates numbering and fonts of the PDF transcripts.
+/// * `load_file_format` - The documents and control numbers of the e-discovery load files.
+/// * `slack_format` - How the Slack export references the stored attachments.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ExtractionOptions {
+    pub export_attachments: bool,
+    pub continue_on_error: bool,
+    pub deduplicate_attachments: bool,
+    pub attachment_path_template: String,
+    pub attachment_filter: AttachmentFilter,
+    pub csv_format: CsvFormat,
+    pub export_formats: Vec<ExportFormat>,
+    pub transcript_format: TranscriptFormat,
+    pub pdf_format: PdfFormat,
+    pub load_file_format: LoadFileFormat,
+    pub slack_format: SlackFormat,
+}
+
+impl ExtractionOptions {
+    /// Sets whether attachments should be written to the `attachments` folder.
+    pub fn export_attachments(mut self, export_attachments: bool) -> Self {
+        self.export_attachments = export_attachments;
+        self
+    }
+
+    /// Sets whether a failed attachment write is recorded and skipped instead of aborting the run.
+    pub fn continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Sets whether attachments are stored once per SHA-256 digest.
+    pub fn deduplicate_attachments(mut self, deduplicate_attachments: bool) -> Self {
+        self.deduplicate_attachments = deduplicate_attachments;
+        self
+    }
+
+    /// Sets the layout of the `attachments` folder, e.g. `{space}/{yyyy}/{mm}/{sender}/{filename}`.
+    pub fn attachment_path_template(mut self, template: impl Into<String>) -> Self {
+        self.attachment_path_template = template.into();
+        self
+    }
+
+    /// Sets the rules deciding which attachments are written.
+    pub fn attachment_filter(mut self, filter: AttachmentFilter) -> Self {
+        self.attachment_filter = filter;
+        self
+    }
+
+    /// Sets the dialect and columns of the CSV outputs.
+    pub fn csv_format(mut self, csv_format: CsvFormat) -> Self {
+        self.csv_format = csv_format;
+        self
+    }
+
+    /// Sets the additional outputs written next to `messages.csv`.
+    pub fn export_formats(mut self, export_formats: Vec<ExportFormat>) -> Self {
+        self.export_formats = export_formats;
+        self
+    }
+
+    /// Sets the layout of the transcript exports.
+    pub fn transcript_format(mut self, transcript_format: TranscriptFormat) -> Self {
+        self.transcript_format = transcript_format;
+        self
+    }
+
+    /// Sets the Bates numbering and fonts of the PDF transcripts.
+    pub fn pdf_format(mut self, pdf_format: PdfFormat) -> Self {
+        self.pdf_format = pdf_format;
+        self
+    }
+
+    /// Sets the documents and control numbers of the e-discovery load files.
+    pub fn load_file_format(mut self, load_file_format: LoadFileFormat) -> Self {
+        self.load_file_format = load_file_format;
+        self
+    }
+
+    /// Sets how the Slack export references the stored attachments.
+    pub fn slack_format(mut self, slack_format: SlackFormat) -> Self {
+        self.slack_format = slack_format;
+        self
+    }
+}
+
+impl Default for ExtractionOptions {
+    fn default() -> Self {
+        Self {
+            export_attachments: false,
+            continue_on_error: true,
+            deduplicate_attachments: false,
+            attachment_path_template: DEFAULT_ATTACHMENT_TEMPLATE.to_string(),
+            attachment_filter: AttachmentFilter::default(),
+            csv_format: CsvFormat::default(),
+            export_formats: Vec::new(),
+            transcript_format: TranscriptFormat::default(),
+            pdf_format: PdfFormat::default(),
+            load_file_format: LoadFileFormat::default(),
+            slack_format: SlackFormat::default(),
+        }
+    }
+}
+
+/// Represents the outcome of a completed extraction run.
+///
+/// # Fields
+///
+/// * `entries` - The number of .mbox entries that were parsed.
+/// * `messages` - The number of messages written to the CSV file.
+/// * `attachments_written` - The number of attachments successfully written to disk.
+/// * `attachments_deduplicated` - The number of attachments that matched an already stored file.
+/// * `attachments_filtered` - The number of attachments left out by the attachment filter.
+/// * `attachments_failed` - The number of attachments that could not be written and were skipped.
+#[derive(Debug, Clone, Default, Serialize)]
+#[non_exhaustive]
+pub struct ExtractionSummary {
+    pub entries: usize,
+    pub messages: usize,
+    pub attachments_written: usize,
+    pub attachments_deduplicated: usize,
+    pub attachments_filtered: usize,
+    pub attachments_failed: usize,
+}
+
+impl ExtractionSummary {
+    /// Builds the human-readable result text shown once processing is finished.
+    pub fn describe(&self, diagnostics: &Diagnostics) -> String {
+        let mut text: String = format!("{} messages from {} entries", self.messages, self.entries);
+        if self.attachments_written > 0 || self.attachments_failed > 0 {
+            text.push_str(&format!(
+                "; {} attachments written, {} failed",
+                self.attachments_written,
+                self.attachments_failed
+            ));
+        }
+        if self.attachments_filtered > 0 {
+            text.push_str(&format!(", {} filtered out", self.attachments_filtered));
+        }
+        if self.attachments_deduplicated > 0 {
+            text.push_str(&format!(", {} duplicates skipped", self.attachments_deduplicated));
+        }
+        format!("{}; {}.", text, diagnostics.summary())
+    }
+}
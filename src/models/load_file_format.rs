@@ -0,0 +1,123 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Represents what a review document of the e-discovery load files holds.
+///
+/// # Variants
+///
+/// * `Conversation` - A whole space or direct message, with its text as a transcript.
+/// * `Message` - A single message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentUnit {
+    Conversation,
+    Message,
+}
+
+impl fmt::Display for DocumentUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DocumentUnit::Conversation => "conversation",
+            DocumentUnit::Message => "message",
+        })
+    }
+}
+
+impl FromStr for DocumentUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "conversation" => Ok(DocumentUnit::Conversation),
+            "message" => Ok(DocumentUnit::Message),
+            other => Err(format!("unknown document unit '{}' (expected 'conversation' or 'message')", other)),
+        }
+    }
+}
+
+/// Represents the documents and control numbers of the e-discovery load files.
+///
+/// Every document gets a control number: `control_prefix` followed by its sequence number, zero-padded to
+/// `control_digits`. Stored attachments become documents of their own, numbered right after their parent.
+///
+/// # Fields
+///
+/// * `unit` - Whether each conversation or each message is a document.
+/// * `custodian` - The custodian of the documents, or an empty String to use the .mbox filename.
+/// * `control_prefix` - The text before the sequence number, e.g. `ACME`.
+/// * `control_start` - The sequence number of the first document.
+/// * `control_digits` - The minimum number of digits of the sequence number.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LoadFileFormat {
+    pub unit: DocumentUnit,
+    pub custodian: String,
+    pub control_prefix: String,
+    pub control_start: u64,
+    pub control_digits: usize,
+}
+
+impl LoadFileFormat {
+    /// Sets whether each conversation or each message is a document.
+    pub fn unit(mut self, unit: DocumentUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Sets the custodian of the documents.
+    pub fn custodian(mut self, custodian: impl Into<String>) -> Self {
+        self.custodian = custodian.into();
+        self
+    }
+
+    /// Sets the text before the control sequence number.
+    pub fn control_prefix(mut self, control_prefix: impl Into<String>) -> Self {
+        self.control_prefix = control_prefix.into();
+        self
+    }
+
+    /// Sets the control sequence number of the first document.
+    pub fn control_start(mut self, control_start: u64) -> Self {
+        self.control_start = control_start;
+        self
+    }
+
+    /// Sets the minimum number of digits of the control sequence number.
+    pub fn control_digits(mut self, control_digits: usize) -> Self {
+        self.control_digits = control_digits;
+        self
+    }
+
+    /// Returns the control number of the document with the given sequence number.
+    pub fn control_number(&self, sequence: u64) -> String {
+        format!("{}{:0width$}", self.control_prefix, sequence, width = self.control_digits)
+    }
+
+    /// Checks that the control numbers can be used as file names and load file values.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the number of digits is not between 1 and 12, or if the prefix
+    /// is longer than 32 characters or contains anything but ASCII letters, digits, `-`, `_` and `.`.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=12).contains(&self.control_digits) {
+            return Err("the number of control number digits must be between 1 and 12".to_string());
+        }
+        if self.control_prefix.len() > 32
+            || !self.control_prefix.chars().all(|c: char| c.is_ascii_alphanumeric() || "-_.".contains(c)) {
+            return Err("the control number prefix must be at most 32 ASCII letters, digits, '-', '_' or '.'".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for LoadFileFormat {
+    fn default() -> Self {
+        Self {
+            unit: DocumentUnit::Conversation,
+            custodian: String::new(),
+            control_prefix: "DOC".to_string(),
+            control_start: 1,
+            control_digits: 8,
+        }
+    }
+}
@@ -1,17 +1,79 @@
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
 use super::{Attachment, Message};
 
 /// Represents an entry in an .mbox file.
 ///
+/// Serializes to the entry's position and headers only: the HTML body is fully represented by the
+/// messages and attachments, which the JSON exports nest themselves (attachments under their owning message).
+///
 /// # Fields
 ///
+/// * `index` - The zero-based position of the entry within the .mbox file.
+/// * `byte_offset` - The byte offset at which the entry starts within the .mbox file.
 /// * `headers` - A String containing the headers of the .mbox entry.
 /// * `html_body` - A String containing the HTML body of the .mbox entry.
 /// * `attachments` - A Vector of Attachment structs representing any attachments in the entry.
 /// * `messages` - A Vector of Message structs representing the individual messages in the entry.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct MboxEntry {
+    pub index: usize,
+    pub byte_offset: usize,
     pub headers: String,
+    #[serde(skip_serializing)]
     pub html_body: String,
+    #[serde(skip_serializing)]
     pub attachments: Vec<Attachment>,
+    #[serde(skip_serializing)]
     pub messages: Vec<Message>,
-}
\ No newline at end of file
+}
+
+impl MboxEntry {
+    /// Returns the value of the first header called `name` (case-insensitive), unfolding continuation lines.
+    pub fn header(&self, name: &str) -> Option<String> {
+        let mut value: Option<String> = None;
+        for line in self.headers.lines() {
+            if let Some(current) = value.as_mut() {
+                if line.starts_with([' ', '\t']) {
+                    current.push(' ');
+                    current.push_str(line.trim());
+                    continue;
+                }
+                break;
+            }
+            if let Some((key, rest)) = line.split_once(':') {
+                if key.eq_ignore_ascii_case(name) {
+                    value = Some(rest.trim().to_string());
+                }
+            }
+        }
+        value
+    }
+
+    /// Returns the name of the space or direct message the entry belongs to, taken from its `Subject` header.
+    pub fn space(&self) -> String {
+        self.header("Subject")
+            .filter(|subject: &String| !subject.is_empty())
+            .unwrap_or_else(|| "Unknown space".to_string())
+    }
+
+    /// Returns the entry's `Date` header as a `DateTime`, or `None` if it is missing or not valid RFC 2822.
+    pub fn date(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc2822(&self.header("Date")?).ok()
+    }
+
+    /// Returns when a message of this entry was sent, falling back to the entry's `Date` header.
+    pub fn sent_at(&self, message: Option<&Message>) -> Option<DateTime<FixedOffset>> {
+        message.and_then(|m: &Message| m.datetime()).or_else(|| self.date())
+    }
+
+    /// Returns the message an attachment belongs to.
+    ///
+    /// Attachments are stored per entry, so this picks the first message whose content mentions
+    /// the attachment filename, falling back to the last message of the entry.
+    pub fn owning_message(&self, attachment: &Attachment) -> Option<&Message> {
+        self.messages.iter()
+            .find(|m: &&Message| m.content.contains(&attachment.filename))
+            .or_else(|| self.messages.last())
+    }
+}
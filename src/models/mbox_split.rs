@@ -0,0 +1,180 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::NaiveDate;
+use serde::Serialize;
+use super::MboxEntry;
+
+/// Represents how the kept entries of an .mbox file are spread over output files.
+///
+/// # Variants
+///
+/// * `Conversation` - A file per space or direct message.
+/// * `Year` - A file per year in which the entries' threads started.
+/// * `Month` - A file per month in which the entries' threads started.
+/// * `Sender` - A file per sender of the first message of the entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    Conversation,
+    Year,
+    Month,
+    Sender,
+}
+
+impl fmt::Display for SplitBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SplitBy::Conversation => "conversation",
+            SplitBy::Year => "year",
+            SplitBy::Month => "month",
+            SplitBy::Sender => "sender",
+        })
+    }
+}
+
+impl FromStr for SplitBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "conversation" => Ok(SplitBy::Conversation),
+            "year" => Ok(SplitBy::Year),
+            "month" => Ok(SplitBy::Month),
+            "sender" => Ok(SplitBy::Sender),
+            other => Err(format!(
+                "unknown split '{}' (expected 'conversation', 'year', 'month' or 'sender')",
+                other
+            )),
+        }
+    }
+}
+
+/// Represents which entries of an .mbox file are written back out, and to which files.
+///
+/// Entries are dated and attributed by the first message of their thread, falling back to the entry's `Date`
+/// header. Every list is optional: an empty list keeps every entry.
+///
+/// # Fields
+///
+/// * `split_by` - How the kept entries are spread over files, or `None` for a single file.
+/// * `max_file_size` - The size in bytes at which a file is continued in a new, numbered one.
+/// * `conversations` - Names of spaces or direct messages of which an entry must belong to one (case-insensitive).
+/// * `senders` - Texts of which a message sender of an entry must contain one (case-insensitive).
+/// * `since` - The first day of the kept entries; undated entries are left out when set.
+/// * `until` - The last day of the kept entries; undated entries are left out when set.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct MboxSplitOptions {
+    pub split_by: Option<SplitBy>,
+    pub max_file_size: Option<u64>,
+    pub conversations: Vec<String>,
+    pub senders: Vec<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl MboxSplitOptions {
+    /// Sets how the kept entries are spread over files.
+    pub fn split_by(mut self, split_by: Option<SplitBy>) -> Self {
+        self.split_by = split_by;
+        self
+    }
+
+    /// Sets the size in bytes at which a file is continued in a new one.
+    pub fn max_file_size(mut self, max_file_size: Option<u64>) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Sets the spaces or direct messages of which an entry must belong to one.
+    pub fn conversations(mut self, conversations: Vec<String>) -> Self {
+        self.conversations = conversations;
+        self
+    }
+
+    /// Sets the texts of which a message sender of an entry must contain one.
+    pub fn senders(mut self, senders: Vec<String>) -> Self {
+        self.senders = senders;
+        self
+    }
+
+    /// Sets the first day of the kept entries.
+    pub fn since(mut self, since: Option<NaiveDate>) -> Self {
+        self.since = since;
+        self
+    }
+
+    /// Sets the last day of the kept entries.
+    pub fn until(mut self, until: Option<NaiveDate>) -> Self {
+        self.until = until;
+        self
+    }
+
+    /// Returns whether any filter is set, so entries that cannot be parsed cannot be kept.
+    pub fn is_filtering(&self) -> bool {
+        !self.conversations.is_empty() || !self.senders.is_empty() || self.since.is_some() || self.until.is_some()
+    }
+
+    /// Returns whether an entry passes every filter.
+    pub fn keeps(&self, entry: &MboxEntry) -> bool {
+        if !self.conversations.is_empty() {
+            let space: String = entry.space();
+            if !self.conversations.iter().any(|conversation: &String| conversation.eq_ignore_ascii_case(&space)) {
+                return false;
+            }
+        }
+        if !self.senders.is_empty() {
+            let senders: Vec<String> = self.senders.iter().map(|sender: &String| sender.to_lowercase()).collect();
+            let matches: bool = entry.messages.iter()
+                .any(|message| senders.iter().any(|sender: &String| message.sender.to_lowercase().contains(sender)));
+            if !matches {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let day: Option<NaiveDate> = entry.sent_at(entry.messages.first()).map(|datetime| datetime.date_naive());
+            return match day {
+                Some(day) => self.since.is_none_or(|since| day >= since) && self.until.is_none_or(|until| day <= until),
+                None => false,
+            };
+        }
+        true
+    }
+
+    /// Checks that the filters can match and that files can hold an entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the maximum file size is 0 or the date range is empty.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_file_size == Some(0) {
+            return Err("the maximum file size must be at least 1 byte".to_string());
+        }
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since > until {
+                return Err(format!("the date range {} to {} is empty", since, until));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Represents the outcome of writing subsets of an .mbox file.
+///
+/// # Fields
+///
+/// * `entries` - The number of entries found in the .mbox file.
+/// * `entries_written` - The number of entries that passed the filters and were written.
+/// * `entries_unparsed` - The number of entries that could not be parsed; they are written unless a filter is set.
+/// * `entries_skipped` - The number of unparsed entries left out because the filters could not be applied to them.
+/// * `bytes_written` - The total size of the written files.
+/// * `files` - The written files, relative to the output folder.
+#[derive(Debug, Clone, Default, Serialize)]
+#[non_exhaustive]
+pub struct MboxSplitSummary {
+    pub entries: usize,
+    pub entries_written: usize,
+    pub entries_unparsed: usize,
+    pub entries_skipped: usize,
+    pub bytes_written: u64,
+    pub files: Vec<String>,
+}
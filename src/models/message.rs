@@ -1,3 +1,6 @@
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
 /// Represents a raw, unparsed message from the .mbox file.
 ///
 /// # Fields
@@ -16,10 +19,17 @@ pub struct RawMessage {
 /// * `sender` - A String containing the name or email address of the message sender.
 /// * `timestamp` - A String containing the timestamp of when the message was sent.
 /// * `content` - A String containing the parsed content of the message.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Message {
     pub message_id: String,
     pub sender: String,
     pub timestamp: String,
     pub content: String,
-}
\ No newline at end of file
+}
+
+impl Message {
+    /// Returns the timestamp as a `DateTime`, or `None` if it could not be parsed into RFC 3339.
+    pub fn datetime(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(&self.timestamp).ok()
+    }
+}
@@ -1,7 +1,27 @@
 mod attachment;
+mod attachment_filter;
+mod csv_format;
+mod diagnostic;
+mod export_format;
+mod extraction;
+mod load_file_format;
 mod mbox_entry;
+mod mbox_split;
 mod message;
+mod pdf_format;
+mod slack_format;
+mod transcript_format;
 
-pub use attachment::Attachment;
+pub use attachment::{Attachment, AttachmentRecord};
+pub use attachment_filter::AttachmentFilter;
+pub use csv_format::{CsvFormat, EXCEL_MAX_ROWS, LineEnding, MessageColumn, QuoteStyle};
+pub use diagnostic::{Diagnostic, DiagnosticKind, Diagnostics};
+pub use export_format::ExportFormat;
+pub use extraction::{ExtractionOptions, ExtractionSummary};
+pub use load_file_format::{DocumentUnit, LoadFileFormat};
 pub use mbox_entry::MboxEntry;
-pub use message::{RawMessage, Message};
\ No newline at end of file
+pub use mbox_split::{MboxSplitOptions, MboxSplitSummary, SplitBy};
+pub use message::{RawMessage, Message};
+pub use pdf_format::PdfFormat;
+pub use slack_format::SlackFormat;
+pub use transcript_format::{TranscriptFormat, DEFAULT_TRANSCRIPT_TIMESTAMP_FORMAT};
@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Represents the Bates numbering and fonts of the PDF transcripts.
+///
+/// Every page carries a Bates number: `bates_prefix` followed by the page's sequence number, zero-padded to
+/// `bates_digits`. The sequence starts at `bates_start` and continues across all transcripts of a run.
+///
+/// # Fields
+///
+/// * `bates_prefix` - The text before the sequence number, e.g. `ACME`.
+/// * `bates_start` - The sequence number of the first page.
+/// * `bates_digits` - The minimum number of digits of the sequence number.
+/// * `fonts` - Single-face TrueType fonts (`glyf` outlines) tried before the bundled ones, e.g. a CJK font; the
+///   first font containing a character is used for it.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PdfFormat {
+    pub bates_prefix: String,
+    pub bates_start: u64,
+    pub bates_digits: usize,
+    pub fonts: Vec<PathBuf>,
+}
+
+impl PdfFormat {
+    /// Sets the text before the Bates sequence number.
+    pub fn bates_prefix(mut self, bates_prefix: impl Into<String>) -> Self {
+        self.bates_prefix = bates_prefix.into();
+        self
+    }
+
+    /// Sets the Bates sequence number of the first page.
+    pub fn bates_start(mut self, bates_start: u64) -> Self {
+        self.bates_start = bates_start;
+        self
+    }
+
+    /// Sets the minimum number of digits of the Bates sequence number.
+    pub fn bates_digits(mut self, bates_digits: usize) -> Self {
+        self.bates_digits = bates_digits;
+        self
+    }
+
+    /// Sets the fonts tried before the bundled ones.
+    pub fn fonts(mut self, fonts: Vec<PathBuf>) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
+    /// Returns the Bates number of the page with the given sequence number.
+    pub fn bates_number(&self, sequence: u64) -> String {
+        format!("{}{:0width$}", self.bates_prefix, sequence, width = self.bates_digits)
+    }
+
+    /// Checks that the Bates numbers fit on a page and that the fonts can be embedded.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the number of digits is not between 1 and 12, if the prefix is
+    /// longer than 32 characters or contains a line break, or if a font file cannot be read or is not a
+    /// single-face TrueType font.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=12).contains(&self.bates_digits) {
+            return Err("the number of Bates digits must be between 1 and 12".to_string());
+        }
+        if self.bates_prefix.chars().count() > 32 || self.bates_prefix.contains(['\r', '\n']) {
+            return Err("the Bates prefix must be a single line of at most 32 characters".to_string());
+        }
+        for font in &self.fonts {
+            check_truetype_font(font)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a font file holds a single font with TrueType outlines.
+///
+/// Fonts are embedded as TrueType programs, which collections (`.ttc`) and fonts with CFF outlines (most `.otf`
+/// files) are not, even though they can be read for layout.
+fn check_truetype_font(path: &Path) -> Result<(), String> {
+    let bytes: Vec<u8> = fs::read(path).map_err(|e| format!("font file '{}' cannot be read: {}", path.display(), e))?;
+    if bytes.starts_with(b"ttcf") {
+        return Err(format!(
+            "font file '{}' is a font collection; extract a single TrueType font (.ttf) from it",
+            path.display()
+        ));
+    }
+
+    // The table directory: a 12-byte header holding the table count, then a 16-byte record per table
+    let table_count: usize = match bytes.get(4..6) {
+        Some(count) => u16::from_be_bytes([count[0], count[1]]) as usize,
+        None => return Err(format!("font file '{}' is not a font", path.display())),
+    };
+    let has_glyf: bool = (0..table_count)
+        .filter_map(|table: usize| bytes.get(12 + table * 16..12 + table * 16 + 4))
+        .any(|tag: &[u8]| tag == b"glyf");
+    if !has_glyf {
+        return Err(format!(
+            "font file '{}' has no TrueType outlines (glyf table); use a TrueType (.ttf) font",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+impl Default for PdfFormat {
+    fn default() -> Self {
+        Self {
+            bates_prefix: String::new(),
+            bates_start: 1,
+            bates_digits: 6,
+            fonts: Vec::new(),
+        }
+    }
+}
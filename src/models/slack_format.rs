@@ -0,0 +1,45 @@
+/// Represents how the Slack export references the stored attachments.
+///
+/// Slack's importer downloads every file from its URL, so the `attachments` folder usually has to be
+/// uploaded somewhere reachable first. Without a base URL, files are referenced by their path relative to the
+/// output folder, which suits migration tools that read the files from disk.
+///
+/// # Fields
+///
+/// * `file_base_url` - The URL the `attachments` folder is published at (e.g. `https://files.example.com/chat/`),
+///   or an empty String to reference files by path.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct SlackFormat {
+    pub file_base_url: String,
+}
+
+impl SlackFormat {
+    /// Sets the URL the `attachments` folder is published at.
+    pub fn file_base_url(mut self, file_base_url: impl Into<String>) -> Self {
+        self.file_base_url = file_base_url.into();
+        self
+    }
+
+    /// Returns the URL or path of a stored attachment, given its path relative to the output folder.
+    pub fn file_url(&self, encoded_path: &str) -> String {
+        if self.file_base_url.is_empty() {
+            return encoded_path.to_string();
+        }
+        let relative: &str = encoded_path.strip_prefix("attachments/").unwrap_or(encoded_path);
+        format!("{}/{}", self.file_base_url.trim_end_matches('/'), relative)
+    }
+
+    /// Checks that the base URL can be handed to Slack.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the base URL is set but is not an http(s) URL.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.file_base_url.is_empty()
+            && !self.file_base_url.starts_with("https://") && !self.file_base_url.starts_with("http://") {
+            return Err(format!("the file base URL '{}' must start with https:// or http://", self.file_base_url));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use chrono::format::{Item, StrftimeItems};
+
+/// The default `strftime` format of the timestamps in the plain-text transcripts.
+pub const DEFAULT_TRANSCRIPT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Represents the layout of the transcript exports (Markdown and plain text).
+///
+/// # Fields
+///
+/// * `split_by_month` - Whether each Markdown conversation is written as one file per month
+///   (`<conversation>/2024-03.md`) instead of a single file.
+/// * `line_width` - The column at which plain-text lines are wrapped, or 0 to never wrap.
+/// * `timestamp_format` - The `strftime` format of the plain-text timestamps, applied in the message's own offset.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TranscriptFormat {
+    pub split_by_month: bool,
+    pub line_width: usize,
+    pub timestamp_format: String,
+}
+
+impl TranscriptFormat {
+    /// Sets whether each Markdown conversation is written as one file per month.
+    pub fn split_by_month(mut self, split_by_month: bool) -> Self {
+        self.split_by_month = split_by_month;
+        self
+    }
+
+    /// Sets the column at which plain-text lines are wrapped, or 0 to never wrap.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Sets the `strftime` format of the plain-text timestamps, e.g. `%d.%m.%Y %H:%M`.
+    pub fn timestamp_format(mut self, timestamp_format: impl Into<String>) -> Self {
+        self.timestamp_format = timestamp_format.into();
+        self
+    }
+
+    /// Checks that the plain-text layout can be rendered.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the timestamp format is empty or not valid `strftime`,
+    /// or if the line width leaves no room for text.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.timestamp_format.is_empty()
+            || StrftimeItems::new(&self.timestamp_format).any(|item: Item| matches!(item, Item::Error)) {
+            return Err(format!("invalid timestamp format '{}'", self.timestamp_format));
+        }
+        if self.line_width != 0 && self.line_width < 20 {
+            return Err("the line width must be 0 (no wrapping) or at least 20".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for TranscriptFormat {
+    fn default() -> Self {
+        Self {
+            split_by_month: false,
+            line_width: 100,
+            timestamp_format: DEFAULT_TRANSCRIPT_TIMESTAMP_FORMAT.to_string(),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::ExtractorError;
 use crate::models::Attachment;
 
 /// Extracts HTML content and attachments from the message body.
@@ -8,24 +9,26 @@ use crate::models::Attachment;
 ///
 /// # Returns
 ///
-/// A tuple containing the HTML body as a String and a vector of Attachment structs.
-pub fn extract_html_and_attachments(body: &str) -> (String, Vec<Attachment>) {
+/// A tuple containing the HTML body as a String, a vector of Attachment structs
+/// and a vector of errors for attachment parts that could not be extracted.
+pub fn extract_html_and_attachments(body: &str) -> (String, Vec<Attachment>, Vec<ExtractorError>) {
     let mut html_body: String = String::new();
     let mut attachments: Vec<Attachment> = Vec::new();
+    let mut errors: Vec<ExtractorError> = Vec::new();
     let parts: Vec<&str> = body.split("--").collect();
 
     for part in parts {
         if part.contains("Content-Type: text/html") {
             html_body = part.to_string();
         } else if part.contains("Content-Disposition: attachment") {
-            let attachment: Option<Attachment> = extract_attachment(part);
-            if let Some(att) = attachment {
-                attachments.push(att);
+            match extract_attachment(part) {
+                Ok(att) => attachments.push(att),
+                Err(e) => errors.push(e),
             }
         }
     }
 
-    (html_body, attachments)
+    (html_body, attachments, errors)
 }
 
 /// Extracts attachment information from a message part.
@@ -36,18 +39,23 @@ pub fn extract_html_and_attachments(body: &str) -> (String, Vec<Attachment>) {
 ///
 /// # Returns
 ///
-/// An Option containing an Attachment struct if successful, or None if extraction fails.
-fn extract_attachment(part: &str) -> Option<Attachment> {
+/// A Result containing an Attachment struct if successful.
+///
+/// # Errors
+///
+/// This function will return an `ExtractorError::Attachment` naming the first missing field
+/// if the part has no content type, filename or base64 content.
+fn extract_attachment(part: &str) -> Result<Attachment, ExtractorError> {
     let mut content_type: String = String::new();
     let mut filename: String = String::new();
     let mut content: String = String::new();
     let mut is_base64: bool = false;
 
     for line in part.lines() {
-        if line.starts_with("Content-Type: ") {
-            content_type = line["Content-Type: ".len()..].to_string();
-        } else if line.starts_with("Content-Disposition: attachment; filename=") {
-            filename = line["Content-Disposition: attachment; filename=".len()..].trim_matches('"').to_string();
+        if let Some(value) = line.strip_prefix("Content-Type: ") {
+            content_type = value.to_string();
+        } else if let Some(value) = line.strip_prefix("Content-Disposition: attachment; filename=") {
+            filename = value.trim_matches('"').to_string();
         } else if line.contains("Content-Transfer-Encoding: base64") {
             is_base64 = true;
         } else if is_base64 && !line.contains(":") {
@@ -55,13 +63,17 @@ fn extract_attachment(part: &str) -> Option<Attachment> {
         }
     }
 
-    if !content_type.is_empty() && !filename.is_empty() && !content.is_empty() {
-        Some(Attachment {
+    if content_type.is_empty() {
+        Err(ExtractorError::Attachment(format!("missing content type (filename '{}')", filename)))
+    } else if filename.is_empty() {
+        Err(ExtractorError::Attachment(format!("missing filename (content type '{}')", content_type)))
+    } else if content.is_empty() {
+        Err(ExtractorError::Attachment(format!("missing base64 content for '{}'", filename)))
+    } else {
+        Ok(Attachment {
             content_type,
             filename,
             content,
         })
-    } else {
-        None
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,12 @@
-use crate::models::{MboxEntry, Message, RawMessage};
+use crate::models::{Diagnostics, DiagnosticKind, MboxEntry, Message, RawMessage};
 use crate::parsers::{extract_html_and_attachments, split_messages, parse_message};
 use crate::utils::decode_quoted_printable;
-use std::error::Error;
 use rayon::prelude::*;
 use core::str::Lines;
 
-/// Splits the content of an .mbox file into individual MboxEntry objects.
+const ENTRY_SEPARATOR: &str = "\nFrom ";
+
+/// Locates the individual entries of an .mbox file without parsing them.
 ///
 /// # Arguments
 ///
@@ -13,48 +14,121 @@ use core::str::Lines;
 ///
 /// # Returns
 ///
-/// A `Result` containing a vector of `MboxEntry` structs if successful, or a boxed error if parsing fails.
+/// A vector of `(byte_offset, entry)` pairs, where `byte_offset` points at the entry's `From ` line
+/// and `entry` is the entry text following the `From ` marker.
+pub fn locate_entries(content: &str) -> Vec<(usize, &str)> {
+    // The separator is ASCII, so every start is a character boundary
+    let starts: Vec<usize> = locate_entry_starts(content.as_bytes());
+
+    starts.iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end: usize = starts.get(i + 1).map(|&next| next - 1).unwrap_or(content.len());
+            (start, &content[start + "From ".len()..end])
+        })
+        .collect()
+}
+
+/// Returns the byte offset of the `From ` line of every entry of an .mbox file, which need not be valid UTF-8.
+pub fn locate_entry_starts(content: &[u8]) -> Vec<usize> {
+    let mut starts: Vec<usize> = Vec::new();
+    if content.starts_with(b"From ") {
+        starts.push(0);
+    }
+    let separator: &[u8] = ENTRY_SEPARATOR.as_bytes();
+    starts.extend(
+        content.iter()
+            .enumerate()
+            .filter(|&(index, &byte)| byte == b'\n' && content[index..].starts_with(separator))
+            .map(|(index, _)| index + 1),
+    );
+    starts
+}
+
+/// Splits the content of an .mbox file into individual MboxEntry objects.
+///
+/// # Arguments
+///
+/// * `content` - A string slice containing the entire content of the .mbox file.
+/// * `diagnostics` - The collector that receives every skipped entry, unparsed message,
+///   failed timestamp and bad attachment.
 ///
-/// # Errors
+/// # Returns
 ///
-/// This function will return an error if any of the parsing steps fail.
-pub fn split_mbox_entries(content: &str) -> Result<Vec<MboxEntry>, Box<dyn Error>> {
-    let parts: Vec<&str> = content.split("\nFrom ").collect();
+/// A vector of `MboxEntry` structs, starting with the entry at the very top of the file.
+/// Problems with individual entries are recorded in `diagnostics` rather than returned.
+pub fn split_mbox_entries(content: &str, diagnostics: &Diagnostics) -> Vec<MboxEntry> {
+    let parts: Vec<(usize, &str)> = locate_entries(content);
+
+    parts.par_iter()
+        .enumerate()
+        .filter_map(|(index, &(byte_offset, part))| parse_entry(index, byte_offset, part, diagnostics))
+        .collect()
+}
 
-    let entries: Vec<MboxEntry> = parts.par_iter().skip(1).filter_map(|part| {
-        let lines: Lines = part.lines();
-        let mut headers: String = String::new();
-        let mut body: String = String::new();
-        let mut in_headers: bool = true;
+/// Parses a single entry located by `locate_entries`, recording its problems in `diagnostics`.
+///
+/// # Returns
+///
+/// The parsed entry, or `None` if its body cannot be decoded (recorded as a skipped entry).
+pub fn parse_entry(index: usize, byte_offset: usize, part: &str, diagnostics: &Diagnostics) -> Option<MboxEntry> {
+    let lines: Lines = part.lines();
+    let mut headers: String = String::new();
+    let mut body: String = String::new();
+    let mut in_headers: bool = true;
 
-        for line in lines {
-            if in_headers {
-                if line.is_empty() {
-                    in_headers = false;
-                } else {
-                    headers.push_str(line);
-                    headers.push('\n');
-                }
+    for line in lines {
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
             } else {
-                body.push_str(line);
-                body.push('\n');
+                headers.push_str(line);
+                headers.push('\n');
             }
+        } else {
+            body.push_str(line);
+            body.push('\n');
         }
+    }
+
+    let (html_body, attachments, attachment_errors) = extract_html_and_attachments(&body);
+    for error in attachment_errors {
+        diagnostics.record(DiagnosticKind::BadAttachment, index, byte_offset, error.to_string());
+    }
 
-        let (html_body, attachments) = extract_html_and_attachments(&body);
-        let decoded_html: String = decode_quoted_printable(&html_body).ok()?;
-        let raw_messages: Vec<RawMessage> = split_messages(&decoded_html);
-        let messages: Vec<Message> = raw_messages.par_iter()
-            .filter_map(|rm: &RawMessage| parse_message(&rm.content))
-            .collect();
-
-        Some(MboxEntry {
-            headers: headers.trim().to_string(),
-            html_body,
-            attachments,
-            messages,
+    let decoded_html: String = match decode_quoted_printable(&html_body) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            diagnostics.record(DiagnosticKind::SkippedEntry, index, byte_offset, e.to_string());
+            return None;
+        }
+    };
+    let raw_messages: Vec<RawMessage> = split_messages(&decoded_html);
+    let messages: Vec<Message> = raw_messages.par_iter()
+        .filter_map(|rm: &RawMessage| match parse_message(&rm.content) {
+            Ok(message) => Some(message),
+            Err(e) => {
+                diagnostics.record(DiagnosticKind::UnparsedMessage, index, byte_offset, e.to_string());
+                None
+            }
         })
-    }).collect();
+        .collect();
+
+    for message in messages.iter().filter(|m: &&Message| m.datetime().is_none()) {
+        diagnostics.record(
+            DiagnosticKind::FailedTimestamp,
+            index,
+            byte_offset,
+            format!("message '{}' has unparseable timestamp '{}'", message.message_id, message.timestamp),
+        );
+    }
 
-    Ok(entries)
-}
\ No newline at end of file
+    Some(MboxEntry {
+        index,
+        byte_offset,
+        headers: headers.trim().to_string(),
+        html_body,
+        attachments,
+        messages,
+    })
+}
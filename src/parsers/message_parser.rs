@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use crate::error::ExtractorError;
 use crate::models::{RawMessage, Message};
 use regex::{Captures, Match, Regex};
 use html_escape::decode_html_entities;
@@ -36,15 +37,31 @@ pub fn split_messages(html: &str) -> Vec<RawMessage> {
 ///
 /// # Returns
 ///
-/// An `Option<Message>` containing the parsed message if successful, or `None` if parsing fails.
-pub fn parse_message(raw_content: &str) -> Option<Message> {
-    let id_regex: Regex = Regex::new(r#"<div data-id="([^"]+)""#).ok()?;
-    let message_id: String = id_regex.captures(raw_content)?.get(1)?.as_str().to_string();
+/// A `Result` containing the parsed message if successful.
+///
+/// # Errors
+///
+/// This function will return an `ExtractorError::MessageParse` naming the first required field
+/// (id, sender or content) that could not be found.
+pub fn parse_message(raw_content: &str) -> Result<Message, ExtractorError> {
+    let missing = |field: &str| ExtractorError::MessageParse(format!("missing {}", field));
+
+    let id_regex: Regex = Regex::new(r#"<div data-id="([^"]+)""#).unwrap();
+    let message_id: String = id_regex.captures(raw_content)
+        .and_then(|cap: Captures| cap.get(1))
+        .ok_or_else(|| missing("message id"))?
+        .as_str()
+        .to_string();
 
-    let sender_regex: Regex = Regex::new(r#"<span style="font-weight:700">(.*?)</span>"#).ok()?;
-    let sender: String = sender_regex.captures(raw_content)?.get(1)?.as_str().trim().to_string();
+    let sender_regex: Regex = Regex::new(r#"<span style="font-weight:700">(.*?)</span>"#).unwrap();
+    let sender: String = sender_regex.captures(raw_content)
+        .and_then(|cap: Captures| cap.get(1))
+        .ok_or_else(|| missing(&format!("sender for message '{}'", message_id)))?
+        .as_str()
+        .trim()
+        .to_string();
 
-    let timestamp_regex: Regex = Regex::new(r#"(?s)<div><span style="font-weight:700">.*?</span>(.*?)</div>"#).ok()?;
+    let timestamp_regex: Regex = Regex::new(r#"(?s)<div><span style="font-weight:700">.*?</span>(.*?)</div>"#).unwrap();
     let raw_timestamp: String = timestamp_regex.captures(raw_content)
         .and_then(|cap: Captures| cap.get(1))
         .map(|m: Match| m.as_str().trim())
@@ -53,12 +70,16 @@ pub fn parse_message(raw_content: &str) -> Option<Message> {
 
     let timestamp: String = parse_and_format_timestamp(&raw_timestamp);
 
-    let content_regex: Regex = Regex::new(r#"white-space:pre-wrap;width:100%">(.*?)</div>"#).ok()?;
-    let content: &str = content_regex.captures(raw_content)?.get(1)?.as_str().trim();
+    let content_regex: Regex = Regex::new(r#"white-space:pre-wrap;width:100%">(.*?)</div>"#).unwrap();
+    let content: &str = content_regex.captures(raw_content)
+        .and_then(|cap: Captures| cap.get(1))
+        .ok_or_else(|| missing(&format!("content for message '{}'", message_id)))?
+        .as_str()
+        .trim();
 
     let clean_content: String = clean_message_content(content);
 
-    Some(Message {
+    Ok(Message {
         message_id,
         sender,
         timestamp,
@@ -74,7 +95,8 @@ pub fn parse_message(raw_content: &str) -> Option<Message> {
 ///
 /// # Returns
 ///
-/// A `String` containing the parsed and formatted timestamp in RFC 3339 format.
+/// A `String` containing the parsed and formatted timestamp in RFC 3339 format,
+/// or the raw timestamp unchanged if it could not be parsed (see `Message::datetime`).
 fn parse_and_format_timestamp(raw_timestamp: &str) -> String {
     // Replace non-breaking space with regular space
     let cleaned_timestamp: String = raw_timestamp.replace('\u{202F}', " ");
@@ -103,7 +125,6 @@ fn parse_and_format_timestamp(raw_timestamp: &str) -> String {
         }
     }
 
-    eprintln!("Failed to parse timestamp '{}'", cleaned_timestamp);
     raw_timestamp.to_string()
 }
 
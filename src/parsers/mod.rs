@@ -3,5 +3,6 @@ mod html_parser;
 mod message_parser;
 
 pub use mbox_parser::split_mbox_entries;
+pub(crate) use mbox_parser::{locate_entry_starts, parse_entry};
 pub use html_parser::extract_html_and_attachments;
-pub use message_parser::{split_messages, parse_message};
\ No newline at end of file
+pub use message_parser::{split_messages, parse_message};
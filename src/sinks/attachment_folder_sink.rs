@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::error::ExtractorError;
+use crate::models::{Attachment, AttachmentRecord, DiagnosticKind, Diagnostics, MboxEntry, Message};
+use crate::utils::{
+    path_to_str, relative_path, render_attachment_path, set_file_modified_time, write_attachment_blob,
+    write_attachment_to_file,
+};
+use super::{AttachmentSink, SinkContext};
+
+/// Writes attachments to the `attachments` folder of the output folder.
+///
+/// Attachments are written either at the path rendered from the attachment path template or, when
+/// deduplicating, under their content hash; later attachments with a hash already written in the run
+/// point at that file, whatever their name. Newly written files get the time the owning message was
+/// sent as their modification time; when that fails, the file is kept and the failure is recorded as a diagnostic.
+#[derive(Default)]
+pub struct AttachmentFolderSink {
+    output_path: PathBuf,
+    deduplicate: bool,
+    path_template: String,
+    diagnostics: Diagnostics,
+    blobs: HashMap<String, PathBuf>,
+}
+
+impl AttachmentFolderSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the folder receiving the attachments.
+    fn folder(&self) -> PathBuf {
+        self.output_path.join("attachments")
+    }
+}
+
+impl AttachmentSink for AttachmentFolderSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.output_path = context.output_path.to_path_buf();
+        self.deduplicate = context.options.deduplicate_attachments;
+        self.path_template = context.options.attachment_path_template.clone();
+        self.diagnostics = context.diagnostics.clone();
+        self.blobs.clear();
+        Ok(())
+    }
+
+    fn attachment(
+        &mut self,
+        entry: &MboxEntry,
+        message: Option<&Message>,
+        attachment: &Attachment,
+        content: &[u8],
+        record: &mut AttachmentRecord,
+    ) -> Result<(), ExtractorError> {
+        let attachments_folder: PathBuf = self.folder();
+        let folder: &str = path_to_str(&attachments_folder)?;
+
+        let (path, duplicate) = if self.deduplicate {
+            match self.blobs.get(&record.sha256) {
+                Some(path) => (path.clone(), true),
+                None => {
+                    let path: PathBuf = write_attachment_blob(attachment, content, &record.sha256, folder)?;
+                    self.blobs.insert(record.sha256.clone(), path.clone());
+                    (path, false)
+                }
+            }
+        } else {
+            let relative: PathBuf = render_attachment_path(&self.path_template, entry, message, attachment);
+            (write_attachment_to_file(&relative, content, folder)?, false)
+        };
+        if !duplicate {
+            if let Some(sent_at) = entry.sent_at(message) {
+                if let Err(e) = set_file_modified_time(&path, sent_at) {
+                    self.diagnostics.record(
+                        DiagnosticKind::FailedModifiedTime,
+                        entry.index,
+                        entry.byte_offset,
+                        format!("'{}': {}", attachment.filename, e),
+                    );
+                }
+            }
+        }
+
+        record.stored_path = relative_path(&path, Path::new(&self.output_path));
+        record.duplicate = duplicate;
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, CsvFormat, MboxEntry};
+use crate::utils::{path_to_str, write_attachments_to_csv, write_attachments_to_json};
+use super::{MessageSink, SinkContext};
+
+/// Writes the record of every attachment to `attachments.csv` and `attachments.json` in the output folder.
+#[derive(Default)]
+pub struct AttachmentManifestSink {
+    output_path: PathBuf,
+    format: CsvFormat,
+    records: Vec<AttachmentRecord>,
+}
+
+impl AttachmentManifestSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for AttachmentManifestSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.output_path = context.output_path.to_path_buf();
+        self.format = context.options.csv_format.clone();
+        self.records.clear();
+        Ok(())
+    }
+
+    fn attachment(&mut self, _entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        write_attachments_to_csv(&self.records, path_to_str(&self.output_path.join("attachments.csv"))?, &self.format)?;
+        write_attachments_to_json(&self.records, path_to_str(&self.output_path.join("attachments.json"))?)
+    }
+}
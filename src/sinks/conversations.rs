@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+use crate::models::{AttachmentRecord, MboxEntry, Message};
+
+/// A message of a transcript with the attachment records it owns.
+pub(super) struct TranscriptMessage {
+    pub entry_index: usize,
+    pub message: Message,
+    pub attachments: Vec<AttachmentRecord>,
+}
+
+/// The messages of a single space or direct message, in file order.
+///
+/// Attachments of entries without messages have no message to belong to and are kept in `attachments`.
+pub(super) struct Conversation {
+    pub space: String,
+    pub messages: Vec<TranscriptMessage>,
+    pub attachments: Vec<AttachmentRecord>,
+}
+
+/// Groups the messages and attachment records of a run by conversation, for the transcript sinks.
+///
+/// Takeout groups the messages of a thread into one .mbox entry, so the sinks that show threads treat the first
+/// message of an entry as the start of a thread and the entry's other messages as its replies. Transcripts link
+/// stored attachments relative to themselves, so their folder (`html`, `markdown`, ...) must stay next to the
+/// `attachments` folder.
+#[derive(Default)]
+pub(super) struct Conversations {
+    conversations: Vec<Conversation>,
+    positions: HashMap<String, usize>,
+}
+
+impl Conversations {
+    pub fn clear(&mut self) {
+        self.conversations.clear();
+        self.positions.clear();
+    }
+
+    pub fn message(&mut self, entry: &MboxEntry, message: &Message) {
+        self.conversation(entry).messages.push(TranscriptMessage {
+            entry_index: entry.index,
+            message: message.clone(),
+            attachments: Vec::new(),
+        });
+    }
+
+    pub fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) {
+        let conversation: &mut Conversation = self.conversation(entry);
+        let owner: Option<&mut TranscriptMessage> = conversation.messages.iter_mut()
+            .rev()
+            .take_while(|m: &&mut TranscriptMessage| m.entry_index == entry.index)
+            .find(|m: &&mut TranscriptMessage| m.message.message_id == record.message_id);
+        match owner {
+            Some(message) => message.attachments.push(record.clone()),
+            None => conversation.attachments.push(record.clone()),
+        }
+    }
+
+    /// Takes the collected conversations, sorted by name.
+    pub fn take_sorted(&mut self) -> Vec<Conversation> {
+        let mut conversations: Vec<Conversation> = std::mem::take(&mut self.conversations);
+        conversations.sort_by(|a: &Conversation, b: &Conversation| a.space.cmp(&b.space));
+        self.positions.clear();
+        conversations
+    }
+
+    fn conversation(&mut self, entry: &MboxEntry) -> &mut Conversation {
+        let space: String = entry.space();
+        let position: usize = match self.positions.get(&space) {
+            Some(position) => *position,
+            None => {
+                self.conversations.push(Conversation { space: space.clone(), messages: Vec::new(), attachments: Vec::new() });
+                self.positions.insert(space, self.conversations.len() - 1);
+                self.conversations.len() - 1
+            }
+        };
+        &mut self.conversations[position]
+    }
+}
+
+/// Hands out unique, filesystem-safe file stems for conversation names.
+///
+/// Names are lowercased and reduced to ASCII letters, digits and dashes; clashes get a numeric suffix.
+#[derive(Default)]
+pub(crate) struct FileStems {
+    taken: HashSet<String>,
+}
+
+impl FileStems {
+    pub fn clear(&mut self) {
+        self.taken.clear();
+    }
+
+    /// Returns a stem for `name` that differs from every stem handed out before.
+    pub fn next(&mut self, name: &str) -> String {
+        let mut slug: String = String::new();
+        for c in name.chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let mut slug: String = slug.chars().take(60).collect::<String>().trim_end_matches('-').to_string();
+        if slug.is_empty() {
+            slug = "conversation".to_string();
+        }
+
+        let mut stem: String = slug.clone();
+        let mut suffix: usize = 2;
+        while !self.taken.insert(stem.clone()) {
+            stem = format!("{}-{}", slug, suffix);
+            suffix += 1;
+        }
+        stem
+    }
+}
+
+/// Returns a unique, filesystem-safe file stem for every conversation, in order (see `FileStems`).
+pub(super) fn file_stems(conversations: &[Conversation]) -> Vec<String> {
+    let mut stems: FileStems = FileStems::default();
+    conversations.iter()
+        .map(|conversation: &Conversation| stems.next(&conversation.space))
+        .collect()
+}
+
+/// Helper function to show a size in bytes in the largest fitting unit
+pub(super) fn format_size(size: usize) -> String {
+    match size {
+        0..1_024 => format!("{} B", size),
+        1_024..1_048_576 => format!("{:.1} KB", size as f64 / 1_024.0),
+        _ => format!("{:.1} MB", size as f64 / 1_048_576.0),
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use regex::Regex;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, MboxEntry, Message};
+use crate::utils::percent_encode_path;
+use super::conversations::{file_stems, format_size, Conversation, Conversations, TranscriptMessage};
+use super::{MessageSink, SinkContext};
+
+/// Keeps the pages from loading scripts, frames or remote content; only local images and inline styles are allowed.
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; img-src 'self' file: data:; style-src 'unsafe-inline'";
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Roboto, Helvetica, Arial, sans-serif; margin: 0 auto; max-width: 52rem; padding: 1.5rem; color: #202124; }
+h1 { font-size: 1.5rem; }
+h2.day { font-size: 0.85rem; color: #5f6368; text-align: center; margin: 2rem 0 1rem; }
+nav { margin-bottom: 1rem; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #e0e0e0; }
+.message { margin: 0.75rem 0; }
+.sender { font-weight: 600; }
+.time { color: #5f6368; font-size: 0.85rem; margin-left: 0.5rem; }
+.content { white-space: pre-wrap; overflow-wrap: anywhere; margin-top: 0.2rem; }
+.attachments { margin-top: 0.4rem; display: flex; flex-wrap: wrap; gap: 0.5rem; }
+.thumb { max-width: 12rem; max-height: 12rem; border: 1px solid #e0e0e0; border-radius: 4px; }
+.missing { color: #5f6368; font-style: italic; }
+";
+
+/// Writes a standalone HTML transcript per conversation to the `html` folder of the output folder,
+/// with an `index.html` listing every conversation.
+///
+/// All message content is escaped, so it is shown as text and can never inject markup or script.
+/// Stored attachments are linked and images are shown as thumbnails.
+#[derive(Default)]
+pub struct HtmlSink {
+    folder: PathBuf,
+    mbox_name: String,
+    conversations: Conversations,
+}
+
+impl HtmlSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for HtmlSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.folder = context.output_path.join("html");
+        self.mbox_name = context.mbox_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        self.conversations.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.conversations.message(entry, message);
+        Ok(())
+    }
+
+    fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.conversations.attachment(entry, record);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        let conversations: Vec<Conversation> = self.conversations.take_sorted();
+        let stems: Vec<String> = file_stems(&conversations);
+        let link_regex: Regex = Regex::new(r#"https?://[^\s<>"]+"#).unwrap();
+
+        fs::create_dir_all(&self.folder)?;
+        for (conversation, stem) in conversations.iter().zip(&stems) {
+            let page: String = render_conversation(conversation, &link_regex);
+            fs::write(self.folder.join(format!("{}.html", stem)), page)?;
+        }
+        fs::write(self.folder.join("index.html"), render_index(&self.mbox_name, &conversations, &stems))?;
+        Ok(())
+    }
+}
+
+/// Renders the page listing every conversation with its number of messages, participants and dates
+fn render_index(mbox_name: &str, conversations: &[Conversation], stems: &[String]) -> String {
+    let mut body: String = String::new();
+    let _ = writeln!(body, "<h1>{}</h1>", encode_text(mbox_name));
+    body.push_str("<table>\n<thead><tr><th>Conversation</th><th>Messages</th><th>Participants</th><th>First message</th><th>Last message</th></tr></thead>\n<tbody>\n");
+    for (conversation, stem) in conversations.iter().zip(stems) {
+        let mut participants: Vec<&str> = conversation.messages.iter()
+            .map(|m: &TranscriptMessage| m.message.sender.as_str())
+            .collect();
+        participants.sort_unstable();
+        participants.dedup();
+        let dates: Vec<DateTime<FixedOffset>> = conversation.messages.iter()
+            .filter_map(|m: &TranscriptMessage| m.message.datetime())
+            .collect();
+        let format_date = |date: Option<&DateTime<FixedOffset>>| -> String {
+            date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default()
+        };
+
+        let _ = writeln!(
+            body,
+            "<tr><td><a href=\"{}.html\">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            encode_double_quoted_attribute(stem),
+            encode_text(&conversation.space),
+            conversation.messages.len(),
+            participants.len(),
+            format_date(dates.iter().min()),
+            format_date(dates.iter().max()),
+        );
+    }
+    body.push_str("</tbody>\n</table>\n");
+    render_page(mbox_name, &body)
+}
+
+/// Renders the transcript of a conversation, with a heading for every day
+fn render_conversation(conversation: &Conversation, link_regex: &Regex) -> String {
+    let mut body: String = String::new();
+    body.push_str("<nav><a href=\"index.html\">&larr; All conversations</a></nav>\n");
+    let _ = writeln!(body, "<h1>{}</h1>", encode_text(&conversation.space));
+
+    let mut current_day: Option<NaiveDate> = None;
+    for transcript_message in &conversation.messages {
+        let message: &Message = &transcript_message.message;
+        let datetime: Option<DateTime<FixedOffset>> = message.datetime();
+        if let Some(day) = datetime.map(|datetime| datetime.date_naive()) {
+            if current_day != Some(day) {
+                let _ = writeln!(body, "<h2 class=\"day\">{}</h2>", day.format("%A, %B %-d, %Y"));
+                current_day = Some(day);
+            }
+        }
+
+        let _ = writeln!(body, "<div class=\"message\" id=\"{}\">", encode_double_quoted_attribute(&message.message_id));
+        let _ = write!(body, "<div><span class=\"sender\">{}</span>", encode_text(&message.sender));
+        match datetime {
+            Some(datetime) => {
+                let _ = write!(
+                    body,
+                    "<time class=\"time\" datetime=\"{}\">{}</time>",
+                    datetime.to_rfc3339(),
+                    datetime.format("%H:%M"),
+                );
+            }
+            None => {
+                let _ = write!(body, "<span class=\"time\">{}</span>", encode_text(&message.timestamp));
+            }
+        }
+        body.push_str("</div>\n");
+        let _ = writeln!(body, "<div class=\"content\">{}</div>", render_content(&message.content, link_regex));
+        render_attachments(&mut body, &transcript_message.attachments);
+        body.push_str("</div>\n");
+    }
+
+    if !conversation.attachments.is_empty() {
+        body.push_str("<h2 class=\"day\">Attachments without a message</h2>\n");
+        render_attachments(&mut body, &conversation.attachments);
+    }
+    render_page(&conversation.space, &body)
+}
+
+/// Escapes the content of a message, turning its http(s) links into anchors
+fn render_content(content: &str, link_regex: &Regex) -> String {
+    let mut html: String = String::new();
+    let mut last: usize = 0;
+    for link in link_regex.find_iter(content) {
+        let url: &str = link.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '\'']);
+        html.push_str(&encode_text(&content[last..link.start()]));
+        let _ = write!(
+            html,
+            "<a href=\"{}\" rel=\"noopener noreferrer\">{}</a>",
+            encode_double_quoted_attribute(url),
+            encode_text(url),
+        );
+        last = link.start() + url.len();
+    }
+    html.push_str(&encode_text(&content[last..]));
+    html
+}
+
+/// Renders the attachments of a message: thumbnails for stored images, links for other stored files
+fn render_attachments(body: &mut String, attachments: &[AttachmentRecord]) {
+    if attachments.is_empty() {
+        return;
+    }
+    body.push_str("<div class=\"attachments\">\n");
+    for record in attachments {
+        let name = encode_text(&record.original_filename);
+        if record.stored_path.is_empty() {
+            let _ = writeln!(body, "<span class=\"missing\">{} (not extracted)</span>", name);
+            continue;
+        }
+
+        let href: String = format!("../{}", percent_encode_path(&record.stored_path));
+        let href = encode_double_quoted_attribute(&href);
+        if record.sniffed_content_type.starts_with("image/") {
+            let _ = writeln!(
+                body,
+                "<a href=\"{}\"><img class=\"thumb\" src=\"{}\" alt=\"{}\" loading=\"lazy\"></a>",
+                href,
+                href,
+                encode_double_quoted_attribute(&record.original_filename),
+            );
+        } else {
+            let _ = writeln!(body, "<a href=\"{}\">{}</a> ({})", href, name, format_size(record.size));
+        }
+    }
+    body.push_str("</div>\n");
+}
+
+/// Wraps a page body in a complete, standalone HTML document
+fn render_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"Content-Security-Policy\" content=\"{}\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        CONTENT_SECURITY_POLICY,
+        encode_text(title),
+        STYLE,
+        body,
+    )
+}
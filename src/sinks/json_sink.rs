@@ -0,0 +1,252 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use serde::Serialize;
+use crate::error::ExtractorError;
+use crate::models::{Attachment, AttachmentRecord, MboxEntry, Message};
+use super::{MessageSink, SinkContext};
+
+/// The version of the JSON and NDJSON schemas, bumped on every incompatible change (see `docs/json-schema.md`).
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// The schema identifier written at the top of `messages.json`.
+const JSON_SCHEMA_NAME: &str = "gchat_mbox_extractor.messages";
+
+/// An entry of `messages.json`, with its messages and their attachments nested.
+#[derive(Serialize)]
+pub(super) struct JsonEntry<'a> {
+    #[serde(flatten)]
+    entry: &'a MboxEntry,
+    space: String,
+    date: Option<String>,
+    messages: Vec<JsonMessage<'a>>,
+    attachments: Vec<JsonAttachment<'a>>,
+}
+
+/// A message with the attachments it owns.
+#[derive(Serialize)]
+pub(super) struct JsonMessage<'a> {
+    #[serde(flatten)]
+    message: &'a Message,
+    attachments: Vec<JsonAttachment<'a>>,
+}
+
+/// An attachment with the outcome of its export, absent when it could not be decoded.
+#[derive(Serialize)]
+pub(super) struct JsonAttachment<'a> {
+    #[serde(flatten)]
+    attachment: &'a Attachment,
+    #[serde(flatten)]
+    export: Option<JsonAttachmentExport<'a>>,
+}
+
+/// The fields of an attachment record that are not already part of the attachment.
+#[derive(Serialize)]
+pub(super) struct JsonAttachmentExport<'a> {
+    stored_path: &'a str,
+    size: usize,
+    sha256: &'a str,
+    sniffed_content_type: &'a str,
+    type_mismatch: bool,
+    duplicate: bool,
+    filter_reason: &'a str,
+}
+
+impl<'a> JsonEntry<'a> {
+    /// Nests the messages and attachment records of an entry, attaching every attachment to its owning
+    /// message. Attachments are listed at the entry level only when the entry has no messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The .mbox entry.
+    /// * `records` - The records of the entry's attachments, in order; attachments that failed have none.
+    pub(super) fn new(entry: &'a MboxEntry, records: &'a [AttachmentRecord]) -> Self {
+        let mut messages: Vec<JsonMessage> = entry.messages.iter()
+            .map(|message: &Message| JsonMessage { message, attachments: Vec::new() })
+            .collect();
+        let mut attachments: Vec<JsonAttachment> = Vec::new();
+
+        for (index, attachment) in entry.attachments.iter().enumerate() {
+            let record: Option<&AttachmentRecord> = records.iter()
+                .find(|record: &&AttachmentRecord| record.attachment_index == index);
+
+            let json_attachment: JsonAttachment = JsonAttachment {
+                attachment,
+                export: record.map(JsonAttachmentExport::new),
+            };
+            let owner: Option<usize> = entry.owning_message(attachment)
+                .and_then(|owner: &Message| entry.messages.iter().position(|m: &Message| std::ptr::eq(m, owner)));
+            match owner {
+                Some(index) => messages[index].attachments.push(json_attachment),
+                None => attachments.push(json_attachment),
+            }
+        }
+
+        Self {
+            entry,
+            space: entry.space(),
+            date: entry.date().map(|date| date.to_rfc3339()),
+            messages,
+            attachments,
+        }
+    }
+
+    /// Splits the entry into one document per message, as written to `messages.ndjson`.
+    pub(super) fn into_lines(self) -> impl Iterator<Item = JsonLine<'a>> {
+        let entry_index: usize = self.entry.index;
+        let space: String = self.space;
+        self.messages.into_iter().map(move |message: JsonMessage<'a>| JsonLine {
+            schema_version: JSON_SCHEMA_VERSION,
+            entry_index,
+            space: space.clone(),
+            message,
+        })
+    }
+}
+
+impl<'a> JsonAttachmentExport<'a> {
+    fn new(record: &'a AttachmentRecord) -> Self {
+        Self {
+            stored_path: &record.stored_path,
+            size: record.size,
+            sha256: &record.sha256,
+            sniffed_content_type: &record.sniffed_content_type,
+            type_mismatch: record.type_mismatch,
+            duplicate: record.duplicate,
+            filter_reason: &record.filter_reason,
+        }
+    }
+}
+
+/// A line of `messages.ndjson`: a single message with its attachments.
+#[derive(Serialize)]
+pub(super) struct JsonLine<'a> {
+    schema_version: u32,
+    entry_index: usize,
+    space: String,
+    #[serde(flatten)]
+    message: JsonMessage<'a>,
+}
+
+/// Streams every entry to `messages.json` in the output folder, as a single document
+/// (see `docs/json-schema.md`).
+#[derive(Default)]
+pub struct JsonSink {
+    writer: Option<BufWriter<File>>,
+    records: Vec<AttachmentRecord>,
+    entries: usize,
+}
+
+impl JsonSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for JsonSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(context.output_path.join("messages.json"))?);
+        write!(
+            writer,
+            "{{\"schema\":{},\"schema_version\":{},\"generator\":{},\"source\":{},\"entries\":[",
+            serde_json::to_string(JSON_SCHEMA_NAME)?,
+            JSON_SCHEMA_VERSION,
+            serde_json::to_string(&generator())?,
+            serde_json::to_string(&source_name(context.mbox_path))?,
+        )?;
+        self.writer = Some(writer);
+        self.records.clear();
+        self.entries = 0;
+        Ok(())
+    }
+
+    fn entry(&mut self, _entry: &MboxEntry) -> Result<(), ExtractorError> {
+        self.records.clear();
+        Ok(())
+    }
+
+    fn attachment(&mut self, _entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn end_entry(&mut self, entry: &MboxEntry) -> Result<(), ExtractorError> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        if self.entries > 0 {
+            writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut *writer, &JsonEntry::new(entry, &self.records))?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.write_all(b"]}\n")?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Streams every message to `messages.ndjson` in the output folder, one JSON document per line
+/// (see `docs/json-schema.md`).
+#[derive(Default)]
+pub struct NdjsonSink {
+    writer: Option<BufWriter<File>>,
+    records: Vec<AttachmentRecord>,
+}
+
+impl NdjsonSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for NdjsonSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.writer = Some(BufWriter::new(File::create(context.output_path.join("messages.ndjson"))?));
+        self.records.clear();
+        Ok(())
+    }
+
+    fn entry(&mut self, _entry: &MboxEntry) -> Result<(), ExtractorError> {
+        self.records.clear();
+        Ok(())
+    }
+
+    fn attachment(&mut self, _entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn end_entry(&mut self, entry: &MboxEntry) -> Result<(), ExtractorError> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        for line in JsonEntry::new(entry, &self.records).into_lines() {
+            serde_json::to_writer(&mut *writer, &line)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Helper function to name the tool and version that wrote a document
+fn generator() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// Helper function to record the .mbox file a document was extracted from, without its folder
+fn source_name(mbox_path: &Path) -> String {
+    mbox_path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
@@ -0,0 +1,442 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, FixedOffset, Utc};
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, DocumentUnit, LoadFileFormat, MboxEntry, Message, TranscriptFormat};
+use super::conversations::{Conversation, Conversations, TranscriptMessage};
+use super::text_sink::render_transcript;
+use super::{MessageSink, SinkContext};
+
+/// The Concordance field separator, ASCII 20 (shown as `¶` by most review tools).
+const FIELD_SEPARATOR: char = '\u{14}';
+/// The Concordance text qualifier, `þ` (ASCII 254).
+const TEXT_QUALIFIER: char = '\u{fe}';
+/// Stands in for line breaks within a Concordance field, `®` (ASCII 174).
+const NEWLINE_MARKER: char = '\u{ae}';
+/// The volume named in the `.opt` image cross-reference.
+const VOLUME: &str = "VOL001";
+
+/// The columns of `loadfile.dat`, in order.
+const DAT_FIELDS: [&str; 20] = [
+    "BEGDOC", "ENDDOC", "BEGATTACH", "ENDATTACH", "PARENTDOC", "ATTACHCOUNT", "CUSTODIAN", "DOCTYPE", "CONVERSATION",
+    "DATESENT", "TIMESENT", "FROM", "PARTICIPANTS", "MESSAGECOUNT", "FILENAME", "FILESIZE", "MIMETYPE", "SHA256",
+    "NATIVEPATH", "TEXTPATH",
+];
+
+/// A review document: a conversation or message with extracted text, or a stored attachment with a native file.
+struct Document {
+    control_number: String,
+    parent: Option<usize>,
+    family_end: usize,
+    attachment_count: usize,
+    conversation: String,
+    sent_at: Option<DateTime<FixedOffset>>,
+    from: String,
+    participants: Vec<String>,
+    message_count: usize,
+    text: Option<String>,
+    attachment: Option<AttachmentRecord>,
+    native_name: String,
+}
+
+impl Document {
+    /// Returns the document type, as written to the `DOCTYPE` field.
+    fn doc_type(&self) -> &'static str {
+        match (&self.attachment, self.message_count) {
+            (Some(_), _) => "Attachment",
+            (None, 1) => "Chat Message",
+            (None, _) => "Chat Conversation",
+        }
+    }
+
+    /// Returns the volume-relative path of the extracted text, or an empty String for attachments.
+    fn text_path(&self) -> String {
+        match self.text {
+            Some(_) => format!("TEXT\\{}.txt", self.control_number),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the volume-relative path of the native file, or an empty String for conversations and messages.
+    fn native_path(&self) -> String {
+        match self.attachment {
+            Some(_) => format!("NATIVES\\{}", self.native_name),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the MIME type of the native file.
+    fn mime_type(&self) -> &str {
+        match &self.attachment {
+            Some(record) if !record.sniffed_content_type.is_empty() => &record.sniffed_content_type,
+            Some(record) => &record.declared_content_type,
+            None => "text/plain",
+        }
+    }
+}
+
+/// Writes e-discovery load files to the `loadfile` folder of the output folder: a Concordance `loadfile.dat`,
+/// a `loadfile.opt` image cross-reference and the same documents as EDRM XML 1.2, `loadfile.xml`.
+///
+/// Each conversation or each message is a document (see `LoadFileFormat::unit`) whose text is written to
+/// `TEXT/<control number>.txt`. Stored attachments are copied to `NATIVES/` as documents of their own, in the
+/// family of their conversation or message; attachments that were not written to disk are left out. The `.opt`
+/// file lists the attachments that are images, the only documents with page images. Paths in the load files are
+/// relative to the `loadfile` folder, which can be handed over as a self-contained volume.
+#[derive(Default)]
+pub struct LoadFileSink {
+    folder: PathBuf,
+    output_path: PathBuf,
+    mbox_name: String,
+    custodian: String,
+    format: LoadFileFormat,
+    transcript_format: TranscriptFormat,
+    conversations: Conversations,
+}
+
+impl LoadFileSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turns the conversations into documents, each followed by its attachments
+    fn documents(&self, conversations: &[Conversation]) -> Vec<Document> {
+        let mut documents: Vec<Document> = Vec::new();
+        for conversation in conversations {
+            let participants: Vec<String> = participants(conversation);
+            match self.format.unit {
+                DocumentUnit::Conversation => {
+                    let first: Option<&Message> = conversation.messages.first().map(|m: &TranscriptMessage| &m.message);
+                    let parent: Document = Document {
+                        sent_at: conversation.messages.iter().find_map(|m: &TranscriptMessage| m.message.datetime()),
+                        from: first.map(|message: &Message| message.sender.clone()).unwrap_or_default(),
+                        message_count: conversation.messages.len(),
+                        text: Some(render_transcript(conversation, &self.transcript_format)),
+                        ..self.document(&conversation.space, &participants, &documents)
+                    };
+                    let attachments = conversation.messages.iter()
+                        .flat_map(|m: &TranscriptMessage| &m.attachments)
+                        .chain(&conversation.attachments);
+                    self.push_family(&mut documents, parent, attachments);
+                }
+                DocumentUnit::Message => {
+                    for transcript_message in &conversation.messages {
+                        let message: &Message = &transcript_message.message;
+                        let parent: Document = Document {
+                            sent_at: message.datetime(),
+                            from: message.sender.clone(),
+                            message_count: 1,
+                            text: Some(render_message_text(&conversation.space, message)),
+                            ..self.document(&conversation.space, &participants, &documents)
+                        };
+                        self.push_family(&mut documents, parent, &transcript_message.attachments);
+                    }
+                    for record in conversation.attachments.iter().filter(|r: &&AttachmentRecord| !r.stored_path.is_empty()) {
+                        let attachment: Document = self.attachment_document(&conversation.space, &participants, None, record, &documents);
+                        documents.push(attachment);
+                    }
+                }
+            }
+        }
+        documents
+    }
+
+    /// Pushes a document followed by its stored attachments, recording the extent of the family
+    fn push_family<'a>(
+        &self,
+        documents: &mut Vec<Document>,
+        parent: Document,
+        attachments: impl IntoIterator<Item = &'a AttachmentRecord>,
+    ) {
+        let parent_index: usize = documents.len();
+        let conversation: String = parent.conversation.clone();
+        let participants: Vec<String> = parent.participants.clone();
+        documents.push(parent);
+        for record in attachments.into_iter().filter(|r: &&AttachmentRecord| !r.stored_path.is_empty()) {
+            let attachment: Document = self.attachment_document(&conversation, &participants, Some(parent_index), record, documents);
+            documents.push(attachment);
+        }
+        let family_end: usize = documents.len() - 1;
+        documents[parent_index].family_end = family_end;
+        documents[parent_index].attachment_count = family_end - parent_index;
+    }
+
+    /// Builds the document of a stored attachment
+    fn attachment_document(
+        &self,
+        conversation: &str,
+        participants: &[String],
+        parent: Option<usize>,
+        record: &AttachmentRecord,
+        documents: &[Document],
+    ) -> Document {
+        let document: Document = self.document(conversation, participants, documents);
+        let extension: String = Path::new(&record.original_filename).extension()
+            .map(|extension| extension.to_string_lossy().chars().filter(char::is_ascii_alphanumeric).collect())
+            .unwrap_or_default();
+        let native_name: String = match extension.is_empty() {
+            true => document.control_number.clone(),
+            false => format!("{}.{}", document.control_number, extension.to_ascii_lowercase()),
+        };
+        let (sent_at, from): (Option<DateTime<FixedOffset>>, String) = match parent {
+            Some(parent) => (documents[parent].sent_at, documents[parent].from.clone()),
+            None => (None, String::new()),
+        };
+        Document { parent, sent_at, from, attachment: Some(record.clone()), native_name, ..document }
+    }
+
+    /// Builds an empty document with the next control number
+    fn document(&self, conversation: &str, participants: &[String], documents: &[Document]) -> Document {
+        let index: usize = documents.len();
+        Document {
+            control_number: self.format.control_number(self.format.control_start + index as u64),
+            parent: None,
+            family_end: index,
+            attachment_count: 0,
+            conversation: conversation.to_string(),
+            sent_at: None,
+            from: String::new(),
+            participants: participants.to_vec(),
+            message_count: 0,
+            text: None,
+            attachment: None,
+            native_name: String::new(),
+        }
+    }
+}
+
+impl MessageSink for LoadFileSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        context.options.load_file_format.validate().map_err(ExtractorError::InvalidOption)?;
+        context.options.transcript_format.validate().map_err(ExtractorError::InvalidOption)?;
+        self.folder = context.output_path.join("loadfile");
+        self.output_path = context.output_path.to_path_buf();
+        self.mbox_name = context.mbox_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        self.custodian = match context.options.load_file_format.custodian.trim() {
+            "" => context.mbox_path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            custodian => custodian.to_string(),
+        };
+        self.format = context.options.load_file_format.clone();
+        self.transcript_format = context.options.transcript_format.clone();
+        self.conversations.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.conversations.message(entry, message);
+        Ok(())
+    }
+
+    fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.conversations.attachment(entry, record);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        let conversations: Vec<Conversation> = self.conversations.take_sorted();
+        let documents: Vec<Document> = self.documents(&conversations);
+
+        let text_folder: PathBuf = self.folder.join("TEXT");
+        let natives_folder: PathBuf = self.folder.join("NATIVES");
+        fs::create_dir_all(&text_folder)?;
+        fs::create_dir_all(&natives_folder)?;
+        for document in &documents {
+            if let Some(text) = &document.text {
+                fs::write(text_folder.join(format!("{}.txt", document.control_number)), text)?;
+            }
+            if let Some(record) = &document.attachment {
+                fs::copy(self.output_path.join(&record.stored_path), natives_folder.join(&document.native_name))?;
+            }
+        }
+
+        fs::write(self.folder.join("loadfile.dat"), render_dat(&documents, &self.custodian))?;
+        fs::write(self.folder.join("loadfile.opt"), render_opt(&documents))?;
+        fs::write(self.folder.join("loadfile.xml"), render_edrm_xml(&documents, &self.custodian, &self.mbox_name))?;
+        Ok(())
+    }
+}
+
+/// Returns the distinct senders of a conversation, sorted
+fn participants(conversation: &Conversation) -> Vec<String> {
+    let mut participants: Vec<String> = conversation.messages.iter()
+        .map(|m: &TranscriptMessage| m.message.sender.clone())
+        .filter(|sender: &String| !sender.is_empty())
+        .collect();
+    participants.sort_unstable();
+    participants.dedup();
+    participants
+}
+
+/// Renders the extracted text of a single message, headed by its conversation, sender and timestamp
+fn render_message_text(conversation: &str, message: &Message) -> String {
+    format!(
+        "Conversation: {}\nFrom: {}\nSent: {}\n\n{}\n",
+        conversation,
+        message.sender,
+        message.timestamp,
+        message.content,
+    )
+}
+
+/// Renders the Concordance `.dat` file: UTF-8 with a byte order mark, CRLF line endings, every field enclosed in
+/// `þ` and separated by ASCII 20, line breaks within fields replaced by `®`. Dates and times are in UTC.
+fn render_dat(documents: &[Document], custodian: &str) -> String {
+    let mut dat: String = String::from('\u{feff}');
+    push_dat_record(&mut dat, DAT_FIELDS.iter().map(|field: &&str| field.to_string()));
+    for document in documents {
+        let family: &Document = match document.parent {
+            Some(parent) => &documents[parent],
+            None => document,
+        };
+        let sent_at: Option<DateTime<Utc>> = document.sent_at.map(|datetime| datetime.with_timezone(&Utc));
+        let record: &Option<AttachmentRecord> = &document.attachment;
+        push_dat_record(&mut dat, [
+            document.control_number.clone(),
+            document.control_number.clone(),
+            family.control_number.clone(),
+            documents[family.family_end].control_number.clone(),
+            document.parent.map(|parent| documents[parent].control_number.clone()).unwrap_or_default(),
+            document.attachment_count.to_string(),
+            custodian.to_string(),
+            document.doc_type().to_string(),
+            document.conversation.clone(),
+            sent_at.map(|datetime| datetime.format("%m/%d/%Y").to_string()).unwrap_or_default(),
+            sent_at.map(|datetime| datetime.format("%H:%M:%S").to_string()).unwrap_or_default(),
+            document.from.clone(),
+            document.participants.join("; "),
+            document.message_count.to_string(),
+            record.as_ref().map(|record| record.original_filename.clone()).unwrap_or_default(),
+            record.as_ref().map(|record| record.size.to_string()).unwrap_or_default(),
+            document.mime_type().to_string(),
+            record.as_ref().map(|record| record.sha256.clone()).unwrap_or_default(),
+            document.native_path(),
+            document.text_path(),
+        ]);
+    }
+    dat
+}
+
+/// Appends a `.dat` record, replacing the delimiters within values so they cannot break the record apart
+fn push_dat_record(dat: &mut String, values: impl IntoIterator<Item = String>) {
+    for (index, value) in values.into_iter().enumerate() {
+        if index > 0 {
+            dat.push(FIELD_SEPARATOR);
+        }
+        dat.push(TEXT_QUALIFIER);
+        let value: String = value.replace("\r\n", "\n").replace(['\r', '\n'], &NEWLINE_MARKER.to_string());
+        dat.push_str(&value.replace([FIELD_SEPARATOR, TEXT_QUALIFIER], " "));
+        dat.push(TEXT_QUALIFIER);
+    }
+    dat.push_str("\r\n");
+}
+
+/// Renders the Opticon `.opt` image cross-reference: `key,volume,path,document break,,,page count` for every
+/// attachment that is an image, each counting as a single page
+fn render_opt(documents: &[Document]) -> String {
+    let mut opt: String = String::new();
+    for document in documents.iter().filter(|document: &&Document| document.mime_type().starts_with("image/")) {
+        let _ = write!(opt, "{},{},{},Y,,,1\r\n", document.control_number, VOLUME, document.native_path());
+    }
+    opt
+}
+
+/// Renders the documents and their attachment relationships as EDRM XML 1.2
+fn render_edrm_xml(documents: &[Document], custodian: &str, mbox_name: &str) -> String {
+    let mut xml: String = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<Root MajorVersion=\"1\" MinorVersion=\"2\" Description=\"Google Chat export of {}\" Locale=\"US\" DataInterchangeType=\"Update\">",
+        escape_xml(mbox_name),
+    );
+    let _ = writeln!(xml, "  <Batch name=\"{}\">\n    <Documents>", escape_xml(mbox_name));
+    for document in documents {
+        let doc_type: &str = if document.attachment.is_some() { "File" } else { "Message" };
+        let _ = writeln!(
+            xml,
+            "      <Document DocID=\"{}\" DocType=\"{}\" MimeType=\"{}\">\n        <Tags>",
+            document.control_number,
+            doc_type,
+            escape_xml(document.mime_type()),
+        );
+        let mut tag = |name: &str, data_type: &str, value: &str| {
+            if !value.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "          <Tag TagName=\"{}\" TagDataType=\"{}\" TagValue=\"{}\"/>",
+                    name,
+                    data_type,
+                    escape_xml(value),
+                );
+            }
+        };
+        tag("#DocType", "Text", document.doc_type());
+        tag("#Conversation", "Text", &document.conversation);
+        tag("#From", "Text", &document.from);
+        tag("#Participants", "Text", &document.participants.join("; "));
+        let sent_at: Option<String> = document.sent_at
+            .map(|datetime| datetime.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ").to_string());
+        tag("#DateSent", "DateTime", sent_at.as_deref().unwrap_or_default());
+        if document.attachment.is_none() {
+            tag("#MessageCount", "Integer", &document.message_count.to_string());
+        }
+        if let Some(record) = &document.attachment {
+            tag("#FileName", "Text", &record.original_filename);
+            tag("#FileSize", "Integer", &record.size.to_string());
+            tag("#SHA256", "Text", &record.sha256);
+        }
+        xml.push_str("        </Tags>\n        <Files>\n");
+        if let Some(text) = &document.text {
+            let _ = writeln!(
+                xml,
+                "          <File FileType=\"Text\">\n            <ExternalFile FilePath=\"TEXT\" FileName=\"{}.txt\" FileSize=\"{}\"/>\n          </File>",
+                document.control_number,
+                text.len(),
+            );
+        }
+        if let Some(record) = &document.attachment {
+            let _ = writeln!(
+                xml,
+                "          <File FileType=\"Native\">\n            <ExternalFile FilePath=\"NATIVES\" FileName=\"{}\" FileSize=\"{}\"/>\n          </File>",
+                escape_xml(&document.native_name),
+                record.size,
+            );
+        }
+        xml.push_str("        </Files>\n        <Locations>\n          <Location>\n");
+        let _ = writeln!(xml, "            <Custodian>{}</Custodian>", escape_xml(custodian));
+        let _ = writeln!(xml, "            <LocationURI>{}</LocationURI>", escape_xml(mbox_name));
+        xml.push_str("          </Location>\n        </Locations>\n      </Document>\n");
+    }
+    xml.push_str("    </Documents>\n    <Relationships>\n");
+    for document in documents {
+        if let Some(parent) = document.parent {
+            let _ = writeln!(
+                xml,
+                "      <Relationship Type=\"Attachment\" ParentDocId=\"{}\" ChildDocId=\"{}\"/>",
+                documents[parent].control_number,
+                document.control_number,
+            );
+        }
+    }
+    xml.push_str("    </Relationships>\n  </Batch>\n</Root>\n");
+    xml
+}
+
+/// Helper function to escape text for XML content and attributes, dropping the characters XML 1.0 cannot represent
+fn escape_xml(text: &str) -> String {
+    let mut escaped: String = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push_str(&format!("&#{};", c as u32)),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use crate::error::ExtractorError;
+use crate::models::{Attachment, MboxEntry};
+use crate::utils::decode_quoted_printable;
+use super::conversations::FileStems;
+use super::{MessageSink, SinkContext};
+
+/// The original headers that describe the MIME structure, which is rebuilt and gets new ones.
+const REPLACED_HEADERS: [&str; 3] = ["mime-version", "content-type", "content-transfer-encoding"];
+
+/// The length of the base64 lines, as allowed by RFC 2045.
+const BASE64_LINE_LENGTH: usize = 76;
+
+/// The file layouts a `MailSink` can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MailLayout {
+    Eml,
+    Mbox,
+}
+
+impl MailLayout {
+    fn folder(&self) -> &'static str {
+        match self {
+            MailLayout::Eml => "eml",
+            MailLayout::Mbox => "mbox",
+        }
+    }
+
+    fn line_ending(&self) -> &'static str {
+        match self {
+            MailLayout::Eml => "\r\n",
+            MailLayout::Mbox => "\n",
+        }
+    }
+}
+
+/// Re-exports the .mbox entries of every conversation as mail that Thunderbird, Outlook or any other mail client
+/// opens: an `.eml` file per entry in `eml/<conversation>/`, or an `.mbox` file per conversation in `mbox/`.
+///
+/// Every message keeps the entry's original headers (`Subject`, `From`, `Date`, ...), while the MIME structure is
+/// rebuilt from the parsed entry: the quoted-printable HTML body, as Takeout writes it, followed by every attachment
+/// found in the entry, whether or not it passed the attachment filter. The `.mbox` files can therefore be extracted
+/// again. Entries are written as they are parsed, so no conversation is held in memory.
+pub struct MailSink {
+    layout: MailLayout,
+    folder: PathBuf,
+    stems: FileStems,
+    conversations: HashMap<String, String>,
+}
+
+impl MailSink {
+    /// Creates a sink writing an `.eml` file per entry.
+    pub fn eml() -> Self {
+        Self::new(MailLayout::Eml)
+    }
+
+    /// Creates a sink writing an `.mbox` file per conversation.
+    pub fn mbox() -> Self {
+        Self::new(MailLayout::Mbox)
+    }
+
+    fn new(layout: MailLayout) -> Self {
+        Self { layout, folder: PathBuf::new(), stems: FileStems::default(), conversations: HashMap::new() }
+    }
+
+    /// Returns the file stem of an entry's conversation, creating its folder or emptying its .mbox file on first use
+    fn conversation_stem(&mut self, entry: &MboxEntry) -> Result<String, ExtractorError> {
+        let space: String = entry.space();
+        if let Some(stem) = self.conversations.get(&space) {
+            return Ok(stem.clone());
+        }
+
+        let stem: String = self.stems.next(&space);
+        match self.layout {
+            MailLayout::Eml => fs::create_dir_all(self.folder.join(&stem))?,
+            MailLayout::Mbox => {
+                File::create(self.folder.join(format!("{}.mbox", stem)))?;
+            }
+        }
+        self.conversations.insert(space, stem.clone());
+        Ok(stem)
+    }
+}
+
+impl MessageSink for MailSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.folder = context.output_path.join(self.layout.folder());
+        self.stems.clear();
+        self.conversations.clear();
+        fs::create_dir_all(&self.folder)?;
+        Ok(())
+    }
+
+    fn entry(&mut self, entry: &MboxEntry) -> Result<(), ExtractorError> {
+        let stem: String = self.conversation_stem(entry)?;
+        let mail: String = render_mail(entry, self.layout.line_ending());
+        match self.layout {
+            MailLayout::Eml => {
+                fs::write(self.folder.join(&stem).join(format!("{:06}.eml", entry.index)), mail)?;
+            }
+            MailLayout::Mbox => {
+                // The entry is built in memory and appended with a single write, so a conversation's file is not
+                // held open across entries (a Takeout can have thousands of conversations)
+                let envelope: &str = entry.headers.lines().next().unwrap_or_default();
+                let mut message: String = String::with_capacity(mail.len() + envelope.len() + 8);
+                message.push_str("From ");
+                message.push_str(envelope);
+                message.push('\n');
+                for line in mail.lines() {
+                    // mboxrd quoting: a body line looking like a separator, quoted or not, gets one more '>'
+                    if line.trim_start_matches('>').starts_with("From ") {
+                        message.push('>');
+                    }
+                    message.push_str(line);
+                    message.push('\n');
+                }
+                message.push('\n');
+
+                let mut file: File = OpenOptions::new().append(true).open(self.folder.join(format!("{}.mbox", stem)))?;
+                file.write_all(message.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders an entry as an RFC 5322 message: its original headers, then a `multipart/mixed` body holding the HTML
+/// body and every attachment
+fn render_mail(entry: &MboxEntry, newline: &str) -> String {
+    let boundary: String = format!("=_gchat_mbox_extractor_{}", entry.index);
+    let mut lines: Vec<String> = Vec::new();
+
+    // The first line is the rest of the `From ` separator line, not a header
+    let mut replaced: bool = false;
+    for line in entry.headers.lines().skip(1) {
+        if !line.starts_with([' ', '\t']) {
+            let name: &str = line.split(':').next().unwrap_or_default().trim();
+            replaced = REPLACED_HEADERS.iter().any(|header: &&str| name.eq_ignore_ascii_case(header));
+        }
+        if !replaced {
+            lines.push(line.to_string());
+        }
+    }
+    lines.push("MIME-Version: 1.0".to_string());
+    lines.push(format!("Content-Type: multipart/mixed; boundary=\"{}\"", boundary));
+    lines.push(String::new());
+    lines.push("This is a multi-part message in MIME format.".to_string());
+
+    // The HTML part is kept with its part headers; its content starts after the first blank line
+    let html: &str = entry.html_body.split_once("\n\n").map(|(_, html)| html).unwrap_or_default();
+    if !html.trim().is_empty() {
+        let decoded: String = decode_quoted_printable(html).unwrap_or_else(|_| html.to_string());
+        lines.push(format!("--{}", boundary));
+        lines.push("Content-Type: text/html; charset=UTF-8".to_string());
+        lines.push("Content-Transfer-Encoding: quoted-printable".to_string());
+        lines.push(String::new());
+        lines.extend(quoted_printable::encode_to_str(decoded.trim_end()).lines().map(str::to_string));
+    }
+
+    for attachment in &entry.attachments {
+        lines.push(format!("--{}", boundary));
+        lines.push(format!("Content-Type: {}", attachment.content_type));
+        lines.push(format!("Content-Disposition: attachment; {}", filename_parameter(attachment)));
+        lines.push("Content-Transfer-Encoding: base64".to_string());
+        lines.push(String::new());
+        push_base64_lines(&mut lines, &attachment.content);
+    }
+    lines.push(format!("--{}--", boundary));
+
+    let mut mail: String = lines.join(newline);
+    mail.push_str(newline);
+    mail
+}
+
+/// Splits base64 content into lines of `BASE64_LINE_LENGTH` characters
+fn push_base64_lines(lines: &mut Vec<String>, content: &str) {
+    let content: String = content.chars().filter(|c: &char| !c.is_whitespace()).collect();
+    for chunk in content.as_bytes().chunks(BASE64_LINE_LENGTH) {
+        lines.push(String::from_utf8_lossy(chunk).to_string());
+    }
+}
+
+/// Returns the quoted `filename` parameter of an attachment, keeping non-ASCII names as UTF-8 like Takeout does
+fn filename_parameter(attachment: &Attachment) -> String {
+    let filename: String = attachment.filename.chars().filter(|c: &char| !c.is_control()).collect();
+    format!("filename=\"{}\"", filename.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, MboxEntry, Message};
+use crate::utils::percent_encode_path;
+use super::conversations::{file_stems, format_size, Conversation, Conversations, TranscriptMessage};
+use super::{MessageSink, SinkContext};
+
+/// The name of the monthly file holding messages whose timestamp could not be converted.
+const UNDATED_FILE_STEM: &str = "undated";
+
+/// Writes a Markdown transcript per conversation to the `markdown` folder of the output folder, or one per
+/// conversation and month when `TranscriptFormat::split_by_month` is set.
+///
+/// Files start with YAML front matter naming the conversation and use a heading per day and a
+/// `**sender** — time` line per message. Message text is kept verbatim apart from escaping raw HTML and
+/// heading markers, so Google Chat's `code` and ```` ``` ```` blocks render as such. Stored attachments are
+/// linked, and images embedded.
+#[derive(Default)]
+pub struct MarkdownSink {
+    folder: PathBuf,
+    split_by_month: bool,
+    conversations: Conversations,
+}
+
+impl MarkdownSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for MarkdownSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.folder = context.output_path.join("markdown");
+        self.split_by_month = context.options.transcript_format.split_by_month;
+        self.conversations.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.conversations.message(entry, message);
+        Ok(())
+    }
+
+    fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.conversations.attachment(entry, record);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        let conversations: Vec<Conversation> = self.conversations.take_sorted();
+        let stems: Vec<String> = file_stems(&conversations);
+
+        fs::create_dir_all(&self.folder)?;
+        for (conversation, stem) in conversations.iter().zip(&stems) {
+            if !self.split_by_month {
+                let messages: Vec<&TranscriptMessage> = conversation.messages.iter().collect();
+                let document: String = render_document(&conversation.space, &messages, &conversation.attachments, "../");
+                fs::write(self.folder.join(format!("{}.md", stem)), document)?;
+                continue;
+            }
+
+            let mut months: BTreeMap<String, Vec<&TranscriptMessage>> = BTreeMap::new();
+            for message in &conversation.messages {
+                let month: String = message.message.datetime()
+                    .map(|datetime| datetime.format("%Y-%m").to_string())
+                    .unwrap_or_else(|| UNDATED_FILE_STEM.to_string());
+                months.entry(month).or_default().push(message);
+            }
+            if !conversation.attachments.is_empty() {
+                months.entry(UNDATED_FILE_STEM.to_string()).or_default();
+            }
+
+            let folder: PathBuf = self.folder.join(stem);
+            fs::create_dir_all(&folder)?;
+            for (month, messages) in &months {
+                let attachments: &[AttachmentRecord] = if month == UNDATED_FILE_STEM { &conversation.attachments } else { &[] };
+                let title: String = format!("{} ({})", conversation.space, month);
+                let document: String = render_document(&title, messages, attachments, "../../");
+                fs::write(folder.join(format!("{}.md", month)), document)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders a transcript with a heading for every day
+///
+/// `root` leads from the file back to the output folder, to link the stored attachments.
+fn render_document(title: &str, messages: &[&TranscriptMessage], attachments: &[AttachmentRecord], root: &str) -> String {
+    let mut document: String = String::new();
+    let _ = writeln!(document, "---\ntitle: {}\n---\n", serde_json::Value::from(title));
+    let _ = writeln!(document, "# {}\n", escape_line(title));
+
+    let mut current_day: Option<NaiveDate> = None;
+    for transcript_message in messages {
+        let message: &Message = &transcript_message.message;
+        let datetime: Option<DateTime<FixedOffset>> = message.datetime();
+        if let Some(day) = datetime.map(|datetime| datetime.date_naive()) {
+            if current_day != Some(day) {
+                let _ = writeln!(document, "## {}\n", day.format("%A, %B %-d, %Y"));
+                current_day = Some(day);
+            }
+        }
+
+        let time: String = datetime
+            .map(|datetime| datetime.format("%H:%M").to_string())
+            .unwrap_or_else(|| message.timestamp.clone());
+        let _ = writeln!(document, "**{}** — {}\n", escape_line(&message.sender), escape_line(&time));
+        let _ = writeln!(document, "{}\n", render_content(&message.content));
+        render_attachments(&mut document, &transcript_message.attachments, root);
+    }
+
+    if !attachments.is_empty() {
+        document.push_str("## Attachments without a message\n\n");
+        render_attachments(&mut document, attachments, root);
+    }
+    document
+}
+
+/// Keeps the text of a message, escaping raw HTML and leading heading markers and turning its
+/// line breaks into Markdown hard breaks outside of code blocks. A code block the message leaves open
+/// is closed, so it cannot swallow the rest of the transcript
+fn render_content(content: &str) -> String {
+    // Every line with whether it is text, which gets a hard break before a following text line
+    let mut lines: Vec<(String, bool)> = Vec::new();
+    let mut in_code_block: bool = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            lines.push((line.to_string(), false));
+        } else if in_code_block {
+            lines.push((line.to_string(), false));
+        } else {
+            lines.push((escape_line(line), true));
+        }
+    }
+    if in_code_block {
+        lines.push(("```".to_string(), false));
+    }
+
+    let mut rendered: String = String::new();
+    for (index, (line, is_text)) in lines.iter().enumerate() {
+        rendered.push_str(line);
+        match lines.get(index + 1) {
+            Some((_, true)) if *is_text => rendered.push_str("  \n"),
+            Some(_) => rendered.push('\n'),
+            None => {}
+        }
+    }
+    rendered
+}
+
+/// Lists the attachments of a message: embedded images and links for other stored files
+fn render_attachments(document: &mut String, attachments: &[AttachmentRecord], root: &str) {
+    if attachments.is_empty() {
+        return;
+    }
+    for record in attachments {
+        let name: String = escape_link_text(&record.original_filename);
+        if record.stored_path.is_empty() {
+            let _ = writeln!(document, "- {} (not extracted)", name);
+        } else if record.sniffed_content_type.starts_with("image/") {
+            let _ = writeln!(document, "- ![{}]({}{})", name, root, percent_encode_path(&record.stored_path));
+        } else {
+            let _ = writeln!(
+                document,
+                "- [{}]({}{}) ({})",
+                name,
+                root,
+                percent_encode_path(&record.stored_path),
+                format_size(record.size),
+            );
+        }
+    }
+    document.push('\n');
+}
+
+/// Helper function to escape raw HTML and a leading heading marker, so text cannot add markup or structure
+fn escape_line(line: &str) -> String {
+    let escaped: String = line.replace('<', "&lt;");
+    let indent: usize = escaped.len() - escaped.trim_start().len();
+    if escaped[indent..].starts_with('#') {
+        return format!("{}\\{}", &escaped[..indent], &escaped[indent..]);
+    }
+    escaped
+}
+
+/// Helper function to escape the characters that would end the text of a link or image
+fn escape_link_text(text: &str) -> String {
+    escape_line(text).replace('[', "\\[").replace(']', "\\]")
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use crate::error::ExtractorError;
+use crate::models::{CsvFormat, MboxEntry, Message};
+use crate::utils::{path_to_str, write_messages_to_csv};
+use super::{MessageSink, SinkContext};
+
+/// Writes every message to `messages.csv` in the output folder.
+#[derive(Default)]
+pub struct MessagesCsvSink {
+    path: PathBuf,
+    format: CsvFormat,
+    messages: Vec<Message>,
+}
+
+impl MessagesCsvSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for MessagesCsvSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.path = context.output_path.join("messages.csv");
+        self.format = context.options.csv_format.clone();
+        self.format.validate().map_err(ExtractorError::InvalidOption)?;
+        self.messages.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, _entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.messages.push(message.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        self.finish_with_progress(&mut |_| {})
+    }
+
+    fn finish_with_progress(&mut self, progress: &mut dyn FnMut(f32)) -> Result<(), ExtractorError> {
+        write_messages_to_csv(&self.messages, path_to_str(&self.path)?, &self.format, |csv_progress: f32| {
+            progress(csv_progress);
+            Ok(())
+        })
+    }
+}
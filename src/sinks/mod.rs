@@ -0,0 +1,178 @@
+//! Output sinks driven by the extraction pipeline.
+//!
+//! For every run, the pipeline calls `begin` on each sink, then walks the .mbox entries in file order,
+//! calling `entry`, `message` for each of the entry's messages, `attachment` for each of its attachments and
+//! `end_entry`, and finally calls `finish`. Any number of sinks can be driven in a single pass.
+
+mod attachment_folder_sink;
+mod attachment_manifest_sink;
+#[cfg(feature = "parquet")]
+mod columnar_sink;
+mod conversations;
+mod html_sink;
+mod json_sink;
+mod load_file_sink;
+mod mail_sink;
+mod markdown_sink;
+mod messages_csv_sink;
+#[cfg(feature = "pdf")]
+mod pdf_sink;
+#[cfg(feature = "sqlite")]
+mod sqlite_sink;
+mod slack_sink;
+mod text_sink;
+#[cfg(feature = "xlsx")]
+mod xlsx_sink;
+
+pub(crate) use conversations::FileStems;
+pub use attachment_folder_sink::AttachmentFolderSink;
+pub use attachment_manifest_sink::AttachmentManifestSink;
+#[cfg(feature = "parquet")]
+pub use columnar_sink::{ColumnarSink, ROWS_PER_GROUP};
+pub use html_sink::HtmlSink;
+pub use json_sink::{JsonSink, NdjsonSink, JSON_SCHEMA_VERSION};
+pub use load_file_sink::LoadFileSink;
+pub use mail_sink::MailSink;
+pub use markdown_sink::MarkdownSink;
+pub use messages_csv_sink::MessagesCsvSink;
+#[cfg(feature = "pdf")]
+pub use pdf_sink::PdfSink;
+#[cfg(feature = "sqlite")]
+pub use sqlite_sink::{SqliteSink, SQLITE_SCHEMA_VERSION};
+pub use slack_sink::SlackSink;
+pub use text_sink::TextSink;
+#[cfg(feature = "xlsx")]
+pub use xlsx_sink::XlsxSink;
+
+use std::path::Path;
+use crate::error::ExtractorError;
+use crate::models::{Attachment, AttachmentRecord, Diagnostics, ExportFormat, ExtractionOptions, MboxEntry, Message};
+
+/// Describes the run a sink is taking part in.
+///
+/// # Fields
+///
+/// * `mbox_path` - The .mbox file being extracted.
+/// * `output_path` - The folder receiving the outputs of the run.
+/// * `options` - The options controlling the run.
+/// * `diagnostics` - The collector of the run, for problems that do not fail a sink; keep a clone to record them later.
+pub struct SinkContext<'a> {
+    pub mbox_path: &'a Path,
+    pub output_path: &'a Path,
+    pub options: &'a ExtractionOptions,
+    pub diagnostics: &'a Diagnostics,
+}
+
+/// Receives the parsed messages and the final attachment records of a run.
+///
+/// Every method has an empty default implementation, so sinks only implement what they need.
+/// An error returned by a message sink aborts the run.
+pub trait MessageSink {
+    /// Called once before the first entry.
+    fn begin(&mut self, _context: &SinkContext) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+
+    /// Called for every .mbox entry, before its messages and attachments.
+    fn entry(&mut self, _entry: &MboxEntry) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+
+    /// Called for every message of an entry.
+    fn message(&mut self, _entry: &MboxEntry, _message: &Message) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+
+    /// Called for every attachment of an entry, once the attachment sinks have handled it.
+    fn attachment(&mut self, _entry: &MboxEntry, _record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+
+    /// Called for every .mbox entry, after its messages and attachments.
+    fn end_entry(&mut self, _entry: &MboxEntry) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+
+    /// Called once after the last entry.
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+
+    /// Called once after the last entry instead of `finish`, with a callback taking the progress of writing the
+    /// output, from 0.0 to 1.0. Sinks writing large outputs on finish override it; by default it calls `finish`.
+    fn finish_with_progress(&mut self, _progress: &mut dyn FnMut(f32)) -> Result<(), ExtractorError> {
+        self.finish()
+    }
+}
+
+/// Stores the decoded content of the attachments that pass the attachment filter.
+///
+/// An error returned by an attachment sink aborts the run unless `continue_on_error` is enabled. Then it is
+/// recorded as a failed attachment write, the later attachment sinks skip the attachment and its record is passed
+/// on as the earlier sinks filled it in.
+pub trait AttachmentSink {
+    /// Called once before the first entry.
+    fn begin(&mut self, _context: &SinkContext) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+
+    /// Called for every attachment that passes the attachment filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The .mbox entry the attachment belongs to.
+    /// * `message` - The message owning the attachment, if any.
+    /// * `attachment` - The attachment as found in the .mbox file.
+    /// * `content` - The decoded content of the attachment.
+    /// * `record` - The manifest record of the attachment; sinks storing the content set `stored_path` and `duplicate`.
+    fn attachment(
+        &mut self,
+        entry: &MboxEntry,
+        message: Option<&Message>,
+        attachment: &Attachment,
+        content: &[u8],
+        record: &mut AttachmentRecord,
+    ) -> Result<(), ExtractorError>;
+
+    /// Called once after the last entry.
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        Ok(())
+    }
+}
+
+/// Builds the sink writing an additional export format.
+///
+/// # Errors
+///
+/// This function will return an `ExtractorError::InvalidOption` if the format needs a cargo feature this build lacks.
+pub(crate) fn export_sink(format: ExportFormat) -> Result<Box<dyn MessageSink + Send>, ExtractorError> {
+    if !format.is_available() {
+        return Err(ExtractorError::InvalidOption(format!(
+            "the {} export is not included in this build",
+            format.name()
+        )));
+    }
+    match format {
+        #[cfg(feature = "xlsx")]
+        ExportFormat::Xlsx => Ok(Box::new(XlsxSink::new())),
+        ExportFormat::Json => Ok(Box::new(JsonSink::new())),
+        ExportFormat::Ndjson => Ok(Box::new(NdjsonSink::new())),
+        ExportFormat::Html => Ok(Box::new(HtmlSink::new())),
+        ExportFormat::Markdown => Ok(Box::new(MarkdownSink::new())),
+        ExportFormat::Text => Ok(Box::new(TextSink::new())),
+        ExportFormat::LoadFile => Ok(Box::new(LoadFileSink::new())),
+        ExportFormat::Eml => Ok(Box::new(MailSink::eml())),
+        ExportFormat::Mbox => Ok(Box::new(MailSink::mbox())),
+        ExportFormat::Slack => Ok(Box::new(SlackSink::new())),
+        #[cfg(feature = "pdf")]
+        ExportFormat::Pdf => Ok(Box::new(PdfSink::new())),
+        #[cfg(feature = "sqlite")]
+        ExportFormat::Sqlite => Ok(Box::new(SqliteSink::new())),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => Ok(Box::new(ColumnarSink::parquet())),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Arrow => Ok(Box::new(ColumnarSink::arrow_ipc())),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!("unavailable formats are rejected above"),
+    }
+}
@@ -0,0 +1,343 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use printpdf::{Color, Greyscale, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerIndex, PdfLayerReference, PdfPageIndex};
+use ttf_parser::Face;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, MboxEntry, Message, PdfFormat};
+use super::conversations::{file_stems, Conversation, Conversations};
+use super::{MessageSink, SinkContext};
+
+/// The fonts embedded in every PDF after the configured ones: DejaVu Sans for most scripts, Noto Emoji for emoji.
+const BUNDLED_FONTS: [&[u8]; 2] = [
+    include_bytes!("../../assets/fonts/DejaVuSans.ttf"),
+    include_bytes!("../../assets/fonts/NotoEmoji-Regular.ttf"),
+];
+
+/// US Letter, in millimetres.
+const PAGE_WIDTH: f32 = 215.9;
+const PAGE_HEIGHT: f32 = 279.4;
+const MARGIN: f32 = 19.0;
+
+const BODY_SIZE: f32 = 10.0;
+const HEADING_SIZE: f32 = 14.0;
+const MARGINAL_SIZE: f32 = 8.0;
+const CONTENT_INDENT: f32 = 4.0;
+const LINE_SPACING: f32 = 1.35;
+const POINT: f32 = 0.352_778;
+
+/// Writes a paginated PDF transcript per conversation to the `pdf` folder of the output folder.
+///
+/// Every page has a header naming the conversation and a footer with its page number and Bates number
+/// (see `PdfFormat`); the Bates sequence continues across the transcripts, in the order of their names.
+/// Fonts are embedded in full, so text renders the same on every machine; characters are drawn with the
+/// first configured or bundled font that contains them. Characters are drawn one glyph each, in logical order and
+/// without shaping, so right-to-left and other complex scripts (Arabic, Hebrew, Devanagari, ...) do not render correctly.
+#[derive(Default)]
+pub struct PdfSink {
+    folder: PathBuf,
+    format: PdfFormat,
+    conversations: Conversations,
+}
+
+impl PdfSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for PdfSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        context.options.pdf_format.validate().map_err(ExtractorError::InvalidOption)?;
+        self.folder = context.output_path.join("pdf");
+        self.format = context.options.pdf_format.clone();
+        self.conversations.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.conversations.message(entry, message);
+        Ok(())
+    }
+
+    fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.conversations.attachment(entry, record);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        let mut font_data: Vec<Cow<[u8]>> = Vec::new();
+        for path in &self.format.fonts {
+            font_data.push(Cow::Owned(fs::read(path)?));
+        }
+        font_data.extend(BUNDLED_FONTS.map(Cow::Borrowed));
+        let mut fonts: Fonts = Fonts::new(&font_data)?;
+
+        let conversations: Vec<Conversation> = self.conversations.take_sorted();
+        let stems: Vec<String> = file_stems(&conversations);
+
+        fs::create_dir_all(&self.folder)?;
+        let mut bates: u64 = self.format.bates_start;
+        for (conversation, stem) in conversations.iter().zip(&stems) {
+            let mut pages: Vec<Page> = layout_conversation(conversation, &mut fonts);
+            add_marginalia(&mut pages, &conversation.space, &mut fonts, &self.format, bates);
+            let document: PdfDocumentReference = render_document(&conversation.space, &pages, &font_data)?;
+            bates += pages.len() as u64;
+            document.save(&mut BufWriter::new(File::create(self.folder.join(format!("{}.pdf", stem)))?))?;
+        }
+        Ok(())
+    }
+}
+
+/// A run of text drawn with a single font.
+struct Run {
+    font: usize,
+    text: String,
+    width: f32,
+}
+
+/// A line of text placed on a page.
+struct Line {
+    x: f32,
+    y: f32,
+    size: f32,
+    grey: bool,
+    runs: Vec<Run>,
+}
+
+/// The lines of a page, and the day heading to bookmark it with.
+#[derive(Default)]
+struct Page {
+    lines: Vec<Line>,
+    bookmark: Option<String>,
+}
+
+/// The parsed fonts, with the font and advance width of every character drawn so far.
+struct Fonts<'a> {
+    faces: Vec<Face<'a>>,
+    glyphs: HashMap<char, (usize, f32)>,
+}
+
+impl<'a> Fonts<'a> {
+    fn new(data: &'a [Cow<[u8]>]) -> Result<Self, ExtractorError> {
+        let faces: Vec<Face> = data.iter()
+            .map(|bytes: &Cow<[u8]>| Face::parse(bytes, 0))
+            .collect::<Result<_, _>>()
+            .map_err(|e| ExtractorError::InvalidOption(format!("a PDF font could not be read: {}", e)))?;
+        Ok(Self { faces, glyphs: HashMap::new() })
+    }
+
+    /// Returns the font drawing a character and its advance width in ems; characters no font contains
+    /// are drawn as U+FFFD
+    fn glyph(&mut self, c: char) -> (usize, f32) {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return *glyph;
+        }
+        let glyph: (usize, f32) = self.faces.iter()
+            .enumerate()
+            .find_map(|(index, face): (usize, &Face)| {
+                let id = face.glyph_index(c)?;
+                let advance: u16 = face.glyph_hor_advance(id)?;
+                Some((index, advance as f32 / face.units_per_em() as f32))
+            })
+            .unwrap_or_else(|| if c == '\u{FFFD}' { (0, 0.5) } else { self.glyph('\u{FFFD}') });
+        self.glyphs.insert(c, glyph);
+        glyph
+    }
+
+    /// Splits text into runs of the same font, measured at the given size in millimetres
+    fn runs(&mut self, text: &str, size: f32) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        for c in text.chars().filter(|c: &char| !c.is_control()) {
+            let (font, advance) = self.glyph(c);
+            let c: char = if self.faces[font].glyph_index(c).is_some() { c } else { '\u{FFFD}' };
+            let width: f32 = advance * size * POINT;
+            match runs.last_mut() {
+                Some(run) if run.font == font => {
+                    run.text.push(c);
+                    run.width += width;
+                }
+                _ => runs.push(Run { font, text: c.to_string(), width }),
+            }
+        }
+        runs
+    }
+
+    fn width(&mut self, text: &str, size: f32) -> f32 {
+        self.runs(text, size).iter().map(|run: &Run| run.width).sum()
+    }
+}
+
+/// Lays out the lines of a conversation from the top of the first page, starting a new page when one is full
+struct Layout<'f, 'a> {
+    fonts: &'f mut Fonts<'a>,
+    pages: Vec<Page>,
+    y: f32,
+}
+
+impl Layout<'_, '_> {
+    fn push(&mut self, text: &str, x: f32, size: f32, grey: bool) {
+        let height: f32 = size * LINE_SPACING * POINT;
+        if self.pages.is_empty() || self.y - height < MARGIN + MARGINAL_SIZE * 2.0 * POINT {
+            self.pages.push(Page::default());
+            self.y = PAGE_HEIGHT - MARGIN - MARGINAL_SIZE * 3.0 * POINT;
+        }
+        self.y -= height;
+        let runs: Vec<Run> = self.fonts.runs(text, size);
+        self.pages.last_mut().unwrap().lines.push(Line { x, y: self.y, size, grey, runs });
+    }
+
+    /// Pushes a paragraph, wrapping it at the right margin
+    fn push_wrapped(&mut self, text: &str, x: f32, size: f32, grey: bool) {
+        let width: f32 = PAGE_WIDTH - MARGIN - x;
+        let space: f32 = self.fonts.width(" ", size);
+        let mut line: String = String::new();
+        let mut line_width: f32 = 0.0;
+        for word in text.split(' ') {
+            let mut word_width: f32 = self.fonts.width(word, size);
+            if !line.is_empty() && line_width + space + word_width > width {
+                self.push(&line, x, size, grey);
+                line.clear();
+                line_width = 0.0;
+            }
+            // Words wider than the page are broken between characters
+            let mut word: &str = word;
+            while word_width > width {
+                let mut fitted: f32 = line_width;
+                let split: usize = word.char_indices()
+                    .find(|(_, c): &(usize, char)| {
+                        fitted += self.fonts.width(&c.to_string(), size);
+                        fitted > width
+                    })
+                    .map_or(word.len(), |(index, _)| index.max(word.chars().next().map_or(1, char::len_utf8)));
+                line.push_str(&word[..split]);
+                self.push(&line, x, size, grey);
+                line.clear();
+                line_width = 0.0;
+                word = &word[split..];
+                word_width = self.fonts.width(word, size);
+            }
+            if !line.is_empty() {
+                line.push(' ');
+                line_width += space;
+            }
+            line.push_str(word);
+            line_width += word_width;
+        }
+        self.push(&line, x, size, grey);
+    }
+
+    fn skip(&mut self, height: f32) {
+        self.y -= height;
+    }
+}
+
+/// Lays out a conversation: its name, a heading for every day and each message with its attachments
+fn layout_conversation(conversation: &Conversation, fonts: &mut Fonts) -> Vec<Page> {
+    let mut layout: Layout = Layout { fonts, pages: Vec::new(), y: 0.0 };
+    layout.push_wrapped(&conversation.space, MARGIN, HEADING_SIZE, false);
+
+    let mut current_day: Option<NaiveDate> = None;
+    for transcript_message in &conversation.messages {
+        let message: &Message = &transcript_message.message;
+        let datetime: Option<DateTime<FixedOffset>> = message.datetime();
+        if let Some(day) = datetime.map(|datetime| datetime.date_naive()) {
+            if current_day != Some(day) {
+                let heading: String = day.format("%A, %B %-d, %Y").to_string();
+                layout.skip(BODY_SIZE * POINT);
+                layout.push(&heading, MARGIN, BODY_SIZE, true);
+                let page: &mut Page = layout.pages.last_mut().unwrap();
+                page.bookmark.get_or_insert(heading);
+                current_day = Some(day);
+            }
+        }
+
+        let time: String = datetime
+            .map(|datetime| datetime.format("%Y-%m-%d %H:%M:%S %:z").to_string())
+            .unwrap_or_else(|| message.timestamp.clone());
+        layout.skip(BODY_SIZE * 0.4 * POINT);
+        layout.push_wrapped(&format!("{} — {}", message.sender, time), MARGIN, BODY_SIZE, false);
+        for line in message.content.lines() {
+            layout.push_wrapped(line, MARGIN + CONTENT_INDENT, BODY_SIZE, false);
+        }
+        layout_attachments(&mut layout, &transcript_message.attachments);
+    }
+
+    if !conversation.attachments.is_empty() {
+        layout.skip(BODY_SIZE * POINT);
+        layout.push("Attachments without a message", MARGIN, BODY_SIZE, true);
+        layout_attachments(&mut layout, &conversation.attachments);
+    }
+    layout.pages
+}
+
+/// Lists the attachments of a message with the path they were extracted to
+fn layout_attachments(layout: &mut Layout, attachments: &[AttachmentRecord]) {
+    for record in attachments {
+        let location: &str = if record.stored_path.is_empty() { "not extracted" } else { &record.stored_path };
+        let text: String = format!("Attachment: {} ({})", record.original_filename, location);
+        layout.push_wrapped(&text, MARGIN + CONTENT_INDENT, MARGINAL_SIZE + 1.0, true);
+    }
+}
+
+/// Adds the header, page number and Bates number to every laid out page
+fn add_marginalia(pages: &mut [Page], title: &str, fonts: &mut Fonts, format: &PdfFormat, first_bates: u64) {
+    let page_count: usize = pages.len();
+    let header_y: f32 = PAGE_HEIGHT - MARGIN;
+    let footer_y: f32 = MARGIN - MARGINAL_SIZE * POINT;
+    for (index, page) in pages.iter_mut().enumerate() {
+        let header: Vec<Run> = fonts.runs(title, MARGINAL_SIZE);
+        page.lines.push(Line { x: MARGIN, y: header_y, size: MARGINAL_SIZE, grey: true, runs: header });
+
+        let page_number: Vec<Run> = fonts.runs(&format!("Page {} of {}", index + 1, page_count), MARGINAL_SIZE);
+        page.lines.push(Line { x: MARGIN, y: footer_y, size: MARGINAL_SIZE, grey: true, runs: page_number });
+
+        let bates: Vec<Run> = fonts.runs(&format.bates_number(first_bates + index as u64), MARGINAL_SIZE);
+        let x: f32 = PAGE_WIDTH - MARGIN - bates.iter().map(|run: &Run| run.width).sum::<f32>();
+        page.lines.push(Line { x, y: footer_y, size: MARGINAL_SIZE, grey: false, runs: bates });
+    }
+}
+
+/// Draws the laid out pages, embedding only the fonts they use
+fn render_document(title: &str, pages: &[Page], font_data: &[Cow<[u8]>]) -> Result<PdfDocumentReference, ExtractorError> {
+    let (document, first_page, first_layer): (PdfDocumentReference, PdfPageIndex, PdfLayerIndex) =
+        PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Transcript");
+    let mut font_refs: Vec<Option<IndirectFontRef>> = vec![None; font_data.len()];
+    for run in pages.iter().flat_map(|page: &Page| &page.lines).flat_map(|line: &Line| &line.runs) {
+        if font_refs[run.font].is_none() {
+            font_refs[run.font] = Some(document.add_external_font(font_data[run.font].as_ref())?);
+        }
+    }
+
+    for (index, page) in pages.iter().enumerate() {
+        let (page_index, layer_index): (PdfPageIndex, PdfLayerIndex) = if index == 0 {
+            (first_page, first_layer)
+        } else {
+            document.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Transcript")
+        };
+        if let Some(bookmark) = &page.bookmark {
+            document.add_bookmark(bookmark.as_str(), page_index);
+        }
+        let layer: PdfLayerReference = document.get_page(page_index).get_layer(layer_index);
+        for line in &page.lines {
+            draw_line(&layer, &font_refs, line);
+        }
+    }
+    Ok(document)
+}
+
+/// Helper function to draw the runs of a line one after the other
+fn draw_line(layer: &PdfLayerReference, fonts: &[Option<IndirectFontRef>], line: &Line) {
+    let shade: f32 = if line.grey { 0.4 } else { 0.0 };
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(shade, None)));
+    let mut x: f32 = line.x;
+    for run in &line.runs {
+        if let Some(font) = &fonts[run.font] {
+            layer.use_text(run.text.as_str(), line.size, Mm(x), Mm(line.y), font);
+        }
+        x += run.width;
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, MboxEntry, Message, SlackFormat};
+use crate::utils::percent_encode_path;
+use super::conversations::{file_stems, Conversation, Conversations, TranscriptMessage};
+use super::{MessageSink, SinkContext};
+
+/// A member of the workspace, as listed in `users.json`.
+#[derive(Serialize)]
+struct SlackUser {
+    id: String,
+    name: String,
+    real_name: String,
+    deleted: bool,
+    is_bot: bool,
+    profile: SlackProfile,
+}
+
+#[derive(Serialize)]
+struct SlackProfile {
+    real_name: String,
+    display_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+}
+
+/// A channel, as listed in `channels.json`.
+#[derive(Serialize)]
+struct SlackChannel {
+    id: String,
+    name: String,
+    created: i64,
+    creator: String,
+    is_archived: bool,
+    is_general: bool,
+    members: Vec<String>,
+    topic: SlackTopic,
+    purpose: SlackTopic,
+}
+
+#[derive(Serialize)]
+struct SlackTopic {
+    value: String,
+    creator: String,
+    last_set: i64,
+}
+
+/// A message of a per-day file, with its thread fields when it starts or replies to a thread.
+#[derive(Serialize)]
+struct SlackMessage {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    user: String,
+    text: String,
+    ts: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_ts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_users: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_reply: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replies: Option<Vec<SlackReply>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    files: Vec<SlackFile>,
+}
+
+#[derive(Serialize)]
+struct SlackReply {
+    user: String,
+    ts: String,
+}
+
+/// A stored attachment shared with a message.
+#[derive(Serialize)]
+struct SlackFile {
+    id: String,
+    created: i64,
+    name: String,
+    title: String,
+    mimetype: String,
+    filetype: String,
+    user: String,
+    size: usize,
+    url_private: String,
+    url_private_download: String,
+}
+
+/// Writes the conversations in the layout of a Slack workspace export to the `slack` folder of the output folder:
+/// `users.json`, `channels.json` and a folder per channel holding a `yyyy-mm-dd.json` file per day (in UTC), ready to
+/// be zipped for Slack's importer or read by migration tools.
+///
+/// Every space and direct message becomes a channel named after it, with the original name as its purpose,
+/// and the replies of a thread become Slack thread replies to its first message. Stored attachments are shared with their message and referenced by path, or by
+/// URL below `SlackFormat::file_base_url`. Messages without a usable timestamp are dated by their entry's `Date`
+/// header; those without either cannot be placed and are left out, as are attachments without a message.
+#[derive(Default)]
+pub struct SlackSink {
+    folder: PathBuf,
+    format: SlackFormat,
+    conversations: Conversations,
+    entry_dates: HashMap<usize, DateTime<FixedOffset>>,
+}
+
+impl SlackSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for SlackSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        context.options.slack_format.validate().map_err(ExtractorError::InvalidOption)?;
+        self.folder = context.output_path.join("slack");
+        self.format = context.options.slack_format.clone();
+        self.conversations.clear();
+        self.entry_dates.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.conversations.message(entry, message);
+        if let Some(date) = entry.date() {
+            self.entry_dates.entry(entry.index).or_insert(date);
+        }
+        Ok(())
+    }
+
+    fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.conversations.attachment(entry, record);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        let conversations: Vec<Conversation> = self.conversations.take_sorted();
+        let channel_names: Vec<String> = file_stems(&conversations);
+
+        let mut senders: Vec<&str> = conversations.iter()
+            .flat_map(|conversation: &Conversation| &conversation.messages)
+            .filter(|m: &&TranscriptMessage| sent_at(m, &self.entry_dates).is_some())
+            .map(|m: &TranscriptMessage| m.message.sender.as_str())
+            .collect();
+        senders.sort_unstable();
+        senders.dedup();
+        let user_ids: HashMap<&str, String> = senders.iter().enumerate()
+            .map(|(index, sender)| (*sender, format!("U{:08X}", index + 1)))
+            .collect();
+        let users: Vec<SlackUser> = senders.iter().map(|sender: &&str| slack_user(sender, &user_ids[sender])).collect();
+
+        fs::create_dir_all(&self.folder)?;
+        let mut channels: Vec<SlackChannel> = Vec::new();
+        let mut file_count: usize = 0;
+        for (index, (conversation, name)) in conversations.iter().zip(&channel_names).enumerate() {
+            let messages: Vec<(DateTime<Utc>, SlackMessage)> = slack_messages(
+                conversation, &self.entry_dates, &user_ids, &self.format, &mut file_count,
+            );
+            let Some((created, first)) = messages.iter().min_by_key(|(datetime, _)| *datetime) else {
+                continue;
+            };
+
+            let mut members: Vec<String> = messages.iter().map(|(_, message)| message.user.clone()).collect();
+            members.sort_unstable();
+            members.dedup();
+            channels.push(SlackChannel {
+                id: format!("C{:08X}", index + 1),
+                name: name.clone(),
+                created: created.timestamp(),
+                creator: first.user.clone(),
+                is_archived: false,
+                is_general: false,
+                members,
+                topic: SlackTopic { value: String::new(), creator: String::new(), last_set: 0 },
+                purpose: SlackTopic {
+                    value: format!("Google Chat: {}", conversation.space),
+                    creator: first.user.clone(),
+                    last_set: created.timestamp(),
+                },
+            });
+
+            let mut days: BTreeMap<String, Vec<(DateTime<Utc>, SlackMessage)>> = BTreeMap::new();
+            for (datetime, message) in messages {
+                days.entry(datetime.format("%Y-%m-%d").to_string()).or_default().push((datetime, message));
+            }
+            let channel_folder: PathBuf = self.folder.join(name);
+            fs::create_dir_all(&channel_folder)?;
+            for (day, mut messages) in days {
+                messages.sort_by_key(|(datetime, _)| *datetime);
+                let messages: Vec<SlackMessage> = messages.into_iter().map(|(_, message)| message).collect();
+                write_json(&channel_folder.join(format!("{}.json", day)), &messages)?;
+            }
+        }
+
+        write_json(&self.folder.join("users.json"), &users)?;
+        write_json(&self.folder.join("channels.json"), &channels)?;
+        Ok(())
+    }
+}
+
+/// Converts the messages of a conversation, linking the replies of every thread to its first message.
+///
+/// Timestamps double as message ids in Slack, so messages sent within the same microsecond are moved apart.
+fn slack_messages(
+    conversation: &Conversation,
+    entry_dates: &HashMap<usize, DateTime<FixedOffset>>,
+    user_ids: &HashMap<&str, String>,
+    format: &SlackFormat,
+    file_count: &mut usize,
+) -> Vec<(DateTime<Utc>, SlackMessage)> {
+    let mut messages: Vec<(DateTime<Utc>, SlackMessage)> = Vec::new();
+    let mut used_timestamps: HashSet<i64> = HashSet::new();
+    let mut thread: Option<(usize, usize)> = None;
+
+    for transcript_message in &conversation.messages {
+        let message: &Message = &transcript_message.message;
+        let Some(datetime) = sent_at(transcript_message, entry_dates) else {
+            continue;
+        };
+        let mut micros: i64 = datetime.timestamp_micros();
+        while !used_timestamps.insert(micros) {
+            micros += 1;
+        }
+        let datetime: DateTime<Utc> = DateTime::from_timestamp_micros(micros).unwrap_or_default();
+        let ts: String = format!("{}.{:06}", micros.div_euclid(1_000_000), micros.rem_euclid(1_000_000));
+        let user: String = user_ids[message.sender.as_str()].clone();
+
+        let files: Vec<SlackFile> = transcript_message.attachments.iter()
+            .filter(|record: &&AttachmentRecord| !record.stored_path.is_empty())
+            .map(|record: &AttachmentRecord| {
+                *file_count += 1;
+                slack_file(record, *file_count, &user, datetime.timestamp(), format)
+            })
+            .collect();
+        let mut slack_message: SlackMessage = SlackMessage {
+            kind: "message",
+            user: user.clone(),
+            text: escape_text(&message.content),
+            ts: ts.clone(),
+            thread_ts: None,
+            parent_user_id: None,
+            reply_count: None,
+            reply_users: None,
+            latest_reply: None,
+            replies: None,
+            files,
+        };
+
+        match thread {
+            Some((entry_index, parent)) if entry_index == transcript_message.entry_index => {
+                let parent_message: &mut SlackMessage = &mut messages[parent].1;
+                slack_message.thread_ts = Some(parent_message.ts.clone());
+                slack_message.parent_user_id = Some(parent_message.user.clone());
+
+                parent_message.thread_ts = Some(parent_message.ts.clone());
+                parent_message.reply_count = Some(parent_message.reply_count.unwrap_or_default() + 1);
+                let reply_users: &mut Vec<String> = parent_message.reply_users.get_or_insert_with(Vec::new);
+                if !reply_users.contains(&user) {
+                    reply_users.push(user.clone());
+                }
+                parent_message.latest_reply = Some(ts.clone());
+                parent_message.replies.get_or_insert_with(Vec::new).push(SlackReply { user, ts });
+            }
+            _ => thread = Some((transcript_message.entry_index, messages.len())),
+        }
+        messages.push((datetime, slack_message));
+    }
+    messages
+}
+
+/// Returns when a message was sent, falling back to the `Date` header of its entry
+fn sent_at(
+    transcript_message: &TranscriptMessage,
+    entry_dates: &HashMap<usize, DateTime<FixedOffset>>,
+) -> Option<DateTime<FixedOffset>> {
+    transcript_message.message.datetime().or_else(|| entry_dates.get(&transcript_message.entry_index).copied())
+}
+
+/// Builds the `users.json` entry of a sender; senders that are email addresses keep them as their email
+fn slack_user(sender: &str, id: &str) -> SlackUser {
+    let real_name: String = if sender.is_empty() { "Unknown sender".to_string() } else { sender.to_string() };
+    let email: Option<String> = Some(sender.to_string()).filter(|sender: &String| sender.contains('@'));
+    let handle: &str = sender.split('@').next().unwrap_or_default();
+    let mut name: String = String::new();
+    for c in handle.to_lowercase().chars() {
+        if c.is_alphanumeric() || "._-".contains(c) {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('.') {
+            name.push('.');
+        }
+    }
+    let name: String = match name.trim_end_matches('.') {
+        "" => id.to_ascii_lowercase(),
+        name => name.to_string(),
+    };
+
+    SlackUser {
+        id: id.to_string(),
+        name: name.clone(),
+        real_name: real_name.clone(),
+        deleted: false,
+        is_bot: false,
+        profile: SlackProfile { real_name, display_name: name, email },
+    }
+}
+
+/// Builds the file object of a stored attachment
+fn slack_file(record: &AttachmentRecord, number: usize, user: &str, created: i64, format: &SlackFormat) -> SlackFile {
+    let url: String = format.file_url(&percent_encode_path(&record.stored_path));
+    let mimetype: &str = if record.sniffed_content_type.is_empty() {
+        &record.declared_content_type
+    } else {
+        &record.sniffed_content_type
+    };
+    // Takeout filenames may carry folders, which Slack would show as part of the name
+    let name: String = Path::new(&record.original_filename).file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| record.original_filename.clone());
+    SlackFile {
+        id: format!("F{:08X}", number),
+        created,
+        filetype: Path::new(&name).extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default(),
+        title: name.clone(),
+        name,
+        mimetype: mimetype.to_string(),
+        user: user.to_string(),
+        size: record.size,
+        url_private: url.clone(),
+        url_private_download: url,
+    }
+}
+
+/// Helper function to escape the characters Slack reserves for its own markup, as its exports do
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Helper function to write a value as pretty-printed JSON
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), ExtractorError> {
+    let json: String = serde_json::to_string_pretty(value)?;
+    fs::write(path, json)?;
+    Ok(())
+}
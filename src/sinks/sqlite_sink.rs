@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, MboxEntry, Message};
+use super::{MessageSink, SinkContext};
+
+/// The version of the database schema, stored as `PRAGMA user_version` (see `docs/sqlite-schema.md`).
+pub const SQLITE_SCHEMA_VERSION: u32 = 1;
+
+/// The tables, indexes and full-text index of the database.
+const SCHEMA: &str = "
+CREATE TABLE spaces (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE users (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE entries (
+    id INTEGER PRIMARY KEY,
+    byte_offset INTEGER NOT NULL,
+    space_id INTEGER NOT NULL REFERENCES spaces(id),
+    date TEXT,
+    headers TEXT NOT NULL
+);
+CREATE TABLE messages (
+    id INTEGER PRIMARY KEY,
+    message_id TEXT NOT NULL,
+    entry_id INTEGER NOT NULL REFERENCES entries(id),
+    space_id INTEGER NOT NULL REFERENCES spaces(id),
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    timestamp TEXT NOT NULL,
+    sent_at TEXT,
+    content TEXT NOT NULL
+);
+CREATE TABLE attachments (
+    id INTEGER PRIMARY KEY,
+    message_id INTEGER REFERENCES messages(id),
+    entry_id INTEGER NOT NULL REFERENCES entries(id),
+    original_filename TEXT NOT NULL,
+    stored_path TEXT NOT NULL,
+    declared_content_type TEXT NOT NULL,
+    sniffed_content_type TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    type_mismatch INTEGER NOT NULL,
+    duplicate INTEGER NOT NULL,
+    filter_reason TEXT NOT NULL
+);
+CREATE TABLE links (
+    id INTEGER PRIMARY KEY,
+    message_id INTEGER NOT NULL REFERENCES messages(id),
+    url TEXT NOT NULL
+);
+CREATE INDEX entries_space ON entries(space_id);
+CREATE INDEX messages_message_id ON messages(message_id);
+CREATE INDEX messages_entry ON messages(entry_id);
+CREATE INDEX messages_space_sent_at ON messages(space_id, sent_at);
+CREATE INDEX messages_user_sent_at ON messages(user_id, sent_at);
+CREATE INDEX messages_sent_at ON messages(sent_at);
+CREATE INDEX attachments_message ON attachments(message_id);
+CREATE INDEX attachments_sha256 ON attachments(sha256);
+CREATE INDEX links_message ON links(message_id);
+CREATE VIRTUAL TABLE messages_fts USING fts5(content, content='messages', content_rowid='id');
+";
+
+/// Writes every entry, message and attachment record to the SQLite database `messages.sqlite` in the
+/// output folder, in a single transaction committed once the run finishes.
+///
+/// An existing database is replaced. If the run fails, the transaction is rolled back and the database is left empty.
+pub struct SqliteSink {
+    connection: Option<Connection>,
+    link_regex: Regex,
+    spaces: HashMap<String, i64>,
+    users: HashMap<String, i64>,
+    entry_messages: HashMap<String, i64>,
+}
+
+impl Default for SqliteSink {
+    fn default() -> Self {
+        Self {
+            connection: None,
+            link_regex: Regex::new(r#"https?://[^\s<>"]+"#).unwrap(),
+            spaces: HashMap::new(),
+            users: HashMap::new(),
+            entry_messages: HashMap::new(),
+        }
+    }
+}
+
+impl SqliteSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the open connection
+    fn connection(&self) -> Result<&Connection, ExtractorError> {
+        self.connection.as_ref().ok_or_else(not_started)
+    }
+
+    /// Returns the id of a space, inserting it on first use
+    fn space_id(&mut self, name: &str) -> Result<i64, ExtractorError> {
+        let connection: &Connection = self.connection.as_ref().ok_or_else(not_started)?;
+        lookup_id(connection, &mut self.spaces, "INSERT INTO spaces (name) VALUES (?1)", name)
+    }
+
+    /// Returns the id of a user, inserting it on first use
+    fn user_id(&mut self, name: &str) -> Result<i64, ExtractorError> {
+        let connection: &Connection = self.connection.as_ref().ok_or_else(not_started)?;
+        lookup_id(connection, &mut self.users, "INSERT INTO users (name) VALUES (?1)", name)
+    }
+
+    /// Finds the http(s) links in a message, without trailing punctuation
+    fn links<'a>(&self, content: &'a str) -> Vec<&'a str> {
+        self.link_regex.find_iter(content)
+            .map(|m: regex::Match| m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '\'']))
+            .collect()
+    }
+}
+
+impl MessageSink for SqliteSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        let path: PathBuf = context.output_path.join("messages.sqlite");
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+
+        let connection: Connection = Connection::open(&path)?;
+        connection.execute_batch("PRAGMA journal_mode = MEMORY; BEGIN;")?;
+        connection.execute_batch(SCHEMA)?;
+        connection.pragma_update(None, "user_version", SQLITE_SCHEMA_VERSION)?;
+
+        self.connection = Some(connection);
+        self.spaces.clear();
+        self.users.clear();
+        Ok(())
+    }
+
+    fn entry(&mut self, entry: &MboxEntry) -> Result<(), ExtractorError> {
+        let space_id: i64 = self.space_id(&entry.space())?;
+        self.connection()?
+            .prepare_cached("INSERT INTO entries (id, byte_offset, space_id, date, headers) VALUES (?1, ?2, ?3, ?4, ?5)")?
+            .execute(params![
+                entry.index as i64,
+                entry.byte_offset as i64,
+                space_id,
+                entry.date().map(|date| date.to_rfc3339()),
+                entry.headers,
+            ])?;
+        self.entry_messages.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        let space_id: i64 = self.space_id(&entry.space())?;
+        let user_id: i64 = self.user_id(&message.sender)?;
+        let sent_at: Option<String> = message.datetime()
+            .map(|datetime| datetime.naive_utc().format("%Y-%m-%d %H:%M:%S").to_string());
+
+        let links: Vec<&str> = self.links(&message.content);
+        let connection: &Connection = self.connection()?;
+        connection
+            .prepare_cached(
+                "INSERT INTO messages (message_id, entry_id, space_id, user_id, timestamp, sent_at, content)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?
+            .execute(params![
+                message.message_id,
+                entry.index as i64,
+                space_id,
+                user_id,
+                message.timestamp,
+                sent_at,
+                message.content,
+            ])?;
+        let message_row: i64 = connection.last_insert_rowid();
+
+        for url in links {
+            connection
+                .prepare_cached("INSERT INTO links (message_id, url) VALUES (?1, ?2)")?
+                .execute(params![message_row, url])?;
+        }
+
+        self.entry_messages.entry(message.message_id.clone()).or_insert(message_row);
+        Ok(())
+    }
+
+    fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        let message_row: Option<i64> = self.entry_messages.get(&record.message_id).copied();
+        self.connection()?
+            .prepare_cached(
+                "INSERT INTO attachments (message_id, entry_id, original_filename, stored_path, declared_content_type,
+                 sniffed_content_type, size, sha256, type_mismatch, duplicate, filter_reason)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?
+            .execute(params![
+                message_row,
+                entry.index as i64,
+                record.original_filename,
+                record.stored_path,
+                record.declared_content_type,
+                record.sniffed_content_type,
+                record.size as i64,
+                record.sha256,
+                record.type_mismatch,
+                record.duplicate,
+                record.filter_reason,
+            ])?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        if let Some(connection) = self.connection.take() {
+            connection.execute_batch("INSERT INTO messages_fts(messages_fts) VALUES ('rebuild'); COMMIT;")?;
+            connection.close().map_err(|(_, e)| e)?;
+        }
+        Ok(())
+    }
+}
+
+/// Helper function to look up the id of a named row, inserting the row on first use
+fn lookup_id(connection: &Connection, cache: &mut HashMap<String, i64>, insert: &str, name: &str) -> Result<i64, ExtractorError> {
+    if let Some(id) = cache.get(name) {
+        return Ok(*id);
+    }
+    connection.prepare_cached(insert)?.execute(params![name])?;
+    let id: i64 = connection.last_insert_rowid();
+    cache.insert(name.to_string(), id);
+    Ok(id)
+}
+
+/// Helper function for the error returned when the sink is used before `begin`
+fn not_started() -> ExtractorError {
+    ExtractorError::InvalidOption("the SQLite sink was used before `begin`".to_string())
+}
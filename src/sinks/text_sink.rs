@@ -0,0 +1,131 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, MboxEntry, Message, TranscriptFormat};
+use super::conversations::{file_stems, Conversation, Conversations, TranscriptMessage};
+use super::{MessageSink, SinkContext};
+
+/// The indentation of continuation lines and of every nesting level.
+const INDENT: &str = "    ";
+
+/// Writes a plain-text transcript per conversation to the `text` folder of the output folder, with a
+/// `[timestamp] sender: message` line per message.
+///
+/// Continuation lines of multi-line or wrapped messages are indented and the replies of a thread are
+/// nested below its first message.
+#[derive(Default)]
+pub struct TextSink {
+    folder: PathBuf,
+    format: TranscriptFormat,
+    conversations: Conversations,
+}
+
+impl TextSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MessageSink for TextSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        context.options.transcript_format.validate().map_err(ExtractorError::InvalidOption)?;
+        self.folder = context.output_path.join("text");
+        self.format = context.options.transcript_format.clone();
+        self.conversations.clear();
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.conversations.message(entry, message);
+        Ok(())
+    }
+
+    fn attachment(&mut self, entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.conversations.attachment(entry, record);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        let conversations: Vec<Conversation> = self.conversations.take_sorted();
+        let stems: Vec<String> = file_stems(&conversations);
+
+        fs::create_dir_all(&self.folder)?;
+        for (conversation, stem) in conversations.iter().zip(&stems) {
+            fs::write(self.folder.join(format!("{}.txt", stem)), render_transcript(conversation, &self.format))?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders a conversation, nesting the replies of every thread below its first message
+pub(super) fn render_transcript(conversation: &Conversation, format: &TranscriptFormat) -> String {
+    let mut transcript: String = String::new();
+    let _ = writeln!(transcript, "{}\n{}\n", conversation.space, "=".repeat(conversation.space.chars().count()));
+
+    let mut thread: Option<usize> = None;
+    for transcript_message in &conversation.messages {
+        let depth: usize = if thread == Some(transcript_message.entry_index) { 1 } else { 0 };
+        thread = Some(transcript_message.entry_index);
+        render_message(&mut transcript, transcript_message, depth, format);
+    }
+
+    if !conversation.attachments.is_empty() {
+        transcript.push_str("\nAttachments without a message:\n");
+        render_attachments(&mut transcript, &conversation.attachments, 1);
+    }
+    transcript
+}
+
+/// Renders a message as `[timestamp] sender: message`, followed by its attachments
+fn render_message(transcript: &mut String, transcript_message: &TranscriptMessage, depth: usize, format: &TranscriptFormat) {
+    let message: &Message = &transcript_message.message;
+    let timestamp: String = message.datetime()
+        .map(|datetime| datetime.format(&format.timestamp_format).to_string())
+        .unwrap_or_else(|| message.timestamp.clone());
+    let prefix: String = format!("[{}] {}: ", timestamp, message.sender);
+
+    let indent: String = INDENT.repeat(depth);
+    let continuation: String = INDENT.repeat(depth + 1);
+    let mut lines = message.content.lines();
+    let first: &str = lines.next().unwrap_or_default();
+    push_wrapped(transcript, &format!("{}{}", indent, prefix), first, &continuation, format.line_width);
+    for line in lines {
+        push_wrapped(transcript, &continuation, line, &continuation, format.line_width);
+    }
+    render_attachments(transcript, &transcript_message.attachments, depth + 1);
+}
+
+/// Lists attachments as `attachment: <stored path>` lines, or their filename when they were not written
+fn render_attachments(transcript: &mut String, attachments: &[AttachmentRecord], depth: usize) {
+    for record in attachments {
+        let location: &str = if record.stored_path.is_empty() { "not extracted" } else { &record.stored_path };
+        let _ = writeln!(transcript, "{}attachment: {} ({})", INDENT.repeat(depth), record.original_filename, location);
+    }
+}
+
+/// Appends `text` after `lead`, wrapping at `width` characters onto lines starting with `continuation`
+fn push_wrapped(transcript: &mut String, lead: &str, text: &str, continuation: &str, width: usize) {
+    let mut line: String = lead.to_string();
+    let mut line_length: usize = line.chars().count();
+    let mut line_has_words: bool = false;
+    for word in text.split(' ') {
+        let word_length: usize = word.chars().count();
+        if width > 0 && line_has_words && line_length + 1 + word_length > width {
+            transcript.push_str(line.trim_end());
+            transcript.push('\n');
+            line = continuation.to_string();
+            line_length = continuation.chars().count();
+            line_has_words = false;
+        }
+        if line_has_words {
+            line.push(' ');
+            line_length += 1;
+        }
+        line.push_str(word);
+        line_length += word_length;
+        line_has_words = true;
+    }
+    transcript.push_str(line.trim_end());
+    transcript.push('\n');
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use chrono::{DateTime, FixedOffset};
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, MboxEntry, Message, EXCEL_MAX_ROWS};
+use crate::utils::{path_to_str, write_workbook_to_xlsx, SummaryValue};
+use super::{MessageSink, SinkContext};
+
+/// Writes every message and attachment record to the Excel workbook `messages.xlsx` in the output folder.
+#[derive(Default)]
+pub struct XlsxSink {
+    path: PathBuf,
+    mbox_path: PathBuf,
+    entries: usize,
+    messages: Vec<(String, Message)>,
+    records: Vec<AttachmentRecord>,
+}
+
+impl XlsxSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the label/value pairs of the Summary sheet
+    fn summary(&self) -> Vec<(String, SummaryValue)> {
+        let spaces: BTreeSet<&str> = self.messages.iter().map(|(space, _)| space.as_str()).collect();
+        let senders: BTreeSet<&str> = self.messages.iter().map(|(_, message)| message.sender.as_str()).collect();
+        let datetimes: Vec<DateTime<FixedOffset>> = self.messages.iter().filter_map(|(_, message)| message.datetime()).collect();
+        let format_datetime = |datetime: Option<&DateTime<FixedOffset>>| -> SummaryValue {
+            SummaryValue::Text(datetime.map(DateTime::to_rfc3339).unwrap_or_default())
+        };
+        let count = |predicate: fn(&AttachmentRecord) -> bool| -> SummaryValue {
+            SummaryValue::Count(self.records.iter().filter(|record: &&AttachmentRecord| predicate(record)).count())
+        };
+
+        vec![
+            ("Source file".to_string(), SummaryValue::Text(self.mbox_path.display().to_string())),
+            ("Entries".to_string(), SummaryValue::Count(self.entries)),
+            ("Messages".to_string(), SummaryValue::Count(self.messages.len())),
+            ("Spaces".to_string(), SummaryValue::Count(spaces.len())),
+            ("Senders".to_string(), SummaryValue::Count(senders.len())),
+            ("First message".to_string(), format_datetime(datetimes.iter().min())),
+            ("Last message".to_string(), format_datetime(datetimes.iter().max())),
+            ("Attachments".to_string(), SummaryValue::Count(self.records.len())),
+            ("Attachments stored".to_string(), count(|record: &AttachmentRecord| !record.stored_path.is_empty())),
+            ("Attachments filtered".to_string(), count(|record: &AttachmentRecord| !record.filter_reason.is_empty())),
+            ("Attachments with mismatched type".to_string(), count(|record: &AttachmentRecord| record.type_mismatch)),
+        ]
+    }
+}
+
+impl MessageSink for XlsxSink {
+    fn begin(&mut self, context: &SinkContext) -> Result<(), ExtractorError> {
+        self.path = context.output_path.join("messages.xlsx");
+        self.mbox_path = context.mbox_path.to_path_buf();
+        self.entries = 0;
+        self.messages.clear();
+        self.records.clear();
+        Ok(())
+    }
+
+    fn entry(&mut self, _entry: &MboxEntry) -> Result<(), ExtractorError> {
+        self.entries += 1;
+        Ok(())
+    }
+
+    fn message(&mut self, entry: &MboxEntry, message: &Message) -> Result<(), ExtractorError> {
+        self.messages.push((entry.space(), message.clone()));
+        Ok(())
+    }
+
+    fn attachment(&mut self, _entry: &MboxEntry, record: &AttachmentRecord) -> Result<(), ExtractorError> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractorError> {
+        let summary: Vec<(String, SummaryValue)> = self.summary();
+        write_workbook_to_xlsx(&self.messages, &self.records, &summary, path_to_str(&self.path)?, EXCEL_MAX_ROWS)
+    }
+}
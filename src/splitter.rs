@@ -0,0 +1,289 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+
+use crate::error::ExtractorError;
+use crate::models::{Diagnostics, MboxEntry, MboxSplitOptions, MboxSplitSummary, Message, SplitBy};
+use crate::parsers::{locate_entry_starts, parse_entry};
+use crate::sinks::FileStems;
+
+/// The name of the file group holding entries without a date, when splitting by year or month.
+const UNDATED_GROUP: &str = "undated";
+
+/// The name of the file group holding entries that could not be parsed, when splitting.
+const UNPARSED_GROUP: &str = "unparsed";
+
+/// Where an entry of the .mbox file goes.
+enum Placement {
+    /// Written to the file group with this name.
+    Group(String),
+    /// Left out by the filters.
+    Filtered,
+    /// Could not be parsed; written to the named group, or left out when a filter is set.
+    Unparsed(Option<String>),
+}
+
+/// Writes the entries of an .mbox file that pass the filters to new .mbox files in the output folder.
+///
+/// Entries are located and parsed as for an extraction, so the filters can look at their conversation, senders
+/// and dates, but each entry is copied byte for byte from its `From ` line up to the next entry, whatever its
+/// encoding. The only byte ever added is a line break after an entry that lacks one (the last entry of a file), so
+/// a following entry still starts on a line of its own. Files are named after their group (`project-space.mbox`,
+/// `2024-03.mbox`) or after the input file when not splitting, with `_1`, `_2`, ... suffixes when a maximum file
+/// size is set. Entries that cannot be parsed are copied as well, to an `unparsed` group when splitting, unless a
+/// filter is set that they cannot be checked against.
+///
+/// Only the raw bytes of the .mbox file are kept in memory; each entry is dropped once its group is known.
+///
+/// # Example
+///
+/// ```no_run
+/// use gchat_mbox_extractor::{split_mbox, MboxSplitOptions, SplitBy};
+///
+/// let options = MboxSplitOptions::default()
+///     .split_by(Some(SplitBy::Conversation))
+///     .max_file_size(Some(1_000_000_000));
+/// let summary = split_mbox("custodian.mbox", "split", &options)?;
+/// println!("{} entries in {} files", summary.entries_written, summary.files.len());
+/// # Ok::<(), gchat_mbox_extractor::ExtractorError>(())
+/// ```
+///
+/// # Errors
+///
+/// This function will return an `ExtractorError::InvalidOption` if the options are unusable or a file would
+/// overwrite the input, and an I/O error if the .mbox file cannot be read or a file cannot be written.
+pub fn split_mbox<P: AsRef<Path>, Q: AsRef<Path>>(
+    mbox_path: P,
+    output_path: Q,
+    options: &MboxSplitOptions,
+) -> Result<MboxSplitSummary, ExtractorError> {
+    options.validate().map_err(ExtractorError::InvalidOption)?;
+    let mbox_path: &Path = mbox_path.as_ref();
+    let output_path: &Path = output_path.as_ref();
+
+    let content: Vec<u8> = fs::read(mbox_path)?;
+    let starts: Vec<usize> = locate_entry_starts(&content);
+    let ranges: Vec<Range<usize>> = starts.iter()
+        .enumerate()
+        .map(|(index, &start)| start..starts.get(index + 1).copied().unwrap_or(content.len()))
+        .collect();
+
+    let input_name: String = mbox_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let diagnostics: Diagnostics = Diagnostics::new();
+    let placements: Vec<Placement> = ranges.par_iter()
+        .enumerate()
+        .map(|(index, range)| {
+            // Invalid UTF-8 only affects what the filters see; the entry itself is copied unchanged
+            let part: Cow<str> = String::from_utf8_lossy(&content[range.start + "From ".len()..range.end]);
+            placement(parse_entry(index, range.start, &part, &diagnostics), options, &input_name)
+        })
+        .collect();
+
+    let mut summary: MboxSplitSummary = MboxSplitSummary { entries: starts.len(), ..MboxSplitSummary::default() };
+    let mut groups: BTreeMap<String, Vec<Range<usize>>> = BTreeMap::new();
+    for (placement, range) in placements.into_iter().zip(ranges) {
+        match placement {
+            Placement::Group(name) => groups.entry(name).or_default().push(range),
+            Placement::Filtered => {}
+            Placement::Unparsed(group) => {
+                summary.entries_unparsed += 1;
+                match group {
+                    Some(name) => groups.entry(name).or_default().push(range),
+                    None => summary.entries_skipped += 1,
+                }
+            }
+        }
+    }
+    fs::create_dir_all(output_path)?;
+    let input: Option<PathBuf> = mbox_path.canonicalize().ok();
+    let mut stems: FileStems = FileStems::default();
+    for (name, ranges) in &groups {
+        let stem: String = stems.next(name);
+        let mut writer: Option<BufWriter<File>> = None;
+        let mut file_size: u64 = 0;
+        let mut part: usize = 0;
+        for range in ranges {
+            let raw: &[u8] = &content[range.clone()];
+            let needs_newline: bool = !raw.ends_with(b"\n");
+            let size: u64 = raw.len() as u64 + needs_newline as u64;
+
+            let file_full: bool = options.max_file_size
+                .is_some_and(|max_size: u64| file_size > 0 && file_size + size > max_size);
+            if writer.is_none() || file_full {
+                if let Some(mut full) = writer.take() {
+                    full.flush()?;
+                }
+                part += 1;
+                let file_name: String = match options.max_file_size {
+                    Some(_) => format!("{}_{}.mbox", stem, part),
+                    None => format!("{}.mbox", stem),
+                };
+                let path: PathBuf = output_path.join(&file_name);
+                if input.is_some() && path.canonicalize().ok() == input {
+                    return Err(ExtractorError::InvalidOption(format!("'{}' would overwrite the input file", path.display())));
+                }
+                writer = Some(BufWriter::new(File::create(path)?));
+                summary.files.push(file_name);
+                file_size = 0;
+            }
+
+            if let Some(writer) = writer.as_mut() {
+                writer.write_all(raw)?;
+                if needs_newline {
+                    writer.write_all(b"\n")?;
+                }
+            }
+            file_size += size;
+            summary.bytes_written += size;
+            summary.entries_written += 1;
+        }
+        if let Some(mut writer) = writer {
+            writer.flush()?;
+        }
+    }
+    Ok(summary)
+}
+
+/// Decides where an entry goes, given the result of parsing it
+fn placement(entry: Option<MboxEntry>, options: &MboxSplitOptions, input_name: &str) -> Placement {
+    match entry {
+        Some(entry) if options.keeps(&entry) => Placement::Group(group_name(&entry, options.split_by, input_name)),
+        Some(_) => Placement::Filtered,
+        None if options.is_filtering() => Placement::Unparsed(None),
+        None => Placement::Unparsed(Some(match options.split_by {
+            Some(_) => UNPARSED_GROUP.to_string(),
+            None => input_name.to_string(),
+        })),
+    }
+}
+
+/// Returns the name of the group an entry is written to
+fn group_name(entry: &MboxEntry, split_by: Option<SplitBy>, input_name: &str) -> String {
+    let first: Option<&Message> = entry.messages.first();
+    match split_by {
+        None => input_name.to_string(),
+        Some(SplitBy::Conversation) => entry.space(),
+        Some(SplitBy::Year) => entry.sent_at(first)
+            .map(|datetime| datetime.format("%Y").to_string())
+            .unwrap_or_else(|| UNDATED_GROUP.to_string()),
+        Some(SplitBy::Month) => entry.sent_at(first)
+            .map(|datetime| datetime.format("%Y-%m").to_string())
+            .unwrap_or_else(|| UNDATED_GROUP.to_string()),
+        Some(SplitBy::Sender) => first
+            .map(|message: &Message| message.sender.clone())
+            .filter(|sender: &String| !sender.is_empty())
+            .unwrap_or_else(|| "unknown sender".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST_ENTRY: &[u8] = b"From 1@xxx Mon Jan 01 00:00:00 2024\nSubject: Space A\nDate: Mon, 1 Jan 2024 10:00:00 +0000\n\nfirst\n\n";
+    const SECOND_ENTRY: &[u8] = b"From 2@xxx Tue Feb 06 00:00:00 2024\nSubject: Space B\nDate: Tue, 6 Feb 2024 10:00:00 +0000\n\nLatin-1 \xe9t\xe9 \xff\n>From quoted\n\n";
+    const LAST_ENTRY: &[u8] = b"From 3@xxx Wed Mar 06 00:00:00 2024\nSubject: Space A\nDate: Wed, 6 Mar 2024 10:00:00 +0000\n\nno final newline";
+
+    /// Creates an empty folder for a test below the system temporary folder.
+    fn test_folder(name: &str) -> PathBuf {
+        let folder: PathBuf = std::env::temp_dir().join(format!("gchat_mbox_extractor_split_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    /// Writes the fixture .mbox file into a test folder and returns its path.
+    fn fixture(folder: &Path) -> PathBuf {
+        let path: PathBuf = folder.join("input.mbox");
+        fs::write(&path, [FIRST_ENTRY, SECOND_ENTRY, LAST_ENTRY].concat()).unwrap();
+        path
+    }
+
+    /// Reads and concatenates the files listed in a summary.
+    fn written(output: &Path, summary: &MboxSplitSummary) -> Vec<u8> {
+        summary.files.iter()
+            .flat_map(|file_name: &String| fs::read(output.join(file_name)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn copies_entries_verbatim() {
+        let folder: PathBuf = test_folder("verbatim");
+        let output: PathBuf = folder.join("out");
+        let summary: MboxSplitSummary = split_mbox(fixture(&folder), &output, &MboxSplitOptions::default()).unwrap();
+
+        assert_eq!(summary.files, vec!["input.mbox".to_string()]);
+        assert_eq!(summary.entries, 3);
+        assert_eq!(summary.entries_written, 3);
+        // The only added byte is the line break ending the last entry
+        let expected: Vec<u8> = [FIRST_ENTRY, SECOND_ENTRY, LAST_ENTRY, b"\n"].concat();
+        assert_eq!(written(&output, &summary), expected);
+        assert_eq!(summary.bytes_written, expected.len() as u64);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn splits_by_conversation_verbatim() {
+        let folder: PathBuf = test_folder("conversation");
+        let output: PathBuf = folder.join("out");
+        let options: MboxSplitOptions = MboxSplitOptions::default().split_by(Some(SplitBy::Conversation));
+        let summary: MboxSplitSummary = split_mbox(fixture(&folder), &output, &options).unwrap();
+
+        assert_eq!(summary.files, vec!["space-a.mbox".to_string(), "space-b.mbox".to_string()]);
+        assert_eq!(fs::read(output.join("space-a.mbox")).unwrap(), [FIRST_ENTRY, LAST_ENTRY, b"\n"].concat());
+        assert_eq!(fs::read(output.join("space-b.mbox")).unwrap(), SECOND_ENTRY);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn rolls_over_at_the_maximum_file_size() {
+        let folder: PathBuf = test_folder("rollover");
+        let output: PathBuf = folder.join("out");
+        let max_size: u64 = (FIRST_ENTRY.len() + SECOND_ENTRY.len()) as u64;
+        let options: MboxSplitOptions = MboxSplitOptions::default().max_file_size(Some(max_size));
+        let summary: MboxSplitSummary = split_mbox(fixture(&folder), &output, &options).unwrap();
+
+        assert_eq!(summary.files, vec!["input_1.mbox".to_string(), "input_2.mbox".to_string()]);
+        assert_eq!(fs::read(output.join("input_1.mbox")).unwrap(), [FIRST_ENTRY, SECOND_ENTRY].concat());
+        assert_eq!(fs::read(output.join("input_2.mbox")).unwrap(), [LAST_ENTRY, b"\n"].concat());
+        assert_eq!(written(&output, &summary), [FIRST_ENTRY, SECOND_ENTRY, LAST_ENTRY, b"\n"].concat());
+
+        // An entry larger than the maximum still gets a file of its own
+        let output: PathBuf = folder.join("small");
+        let options: MboxSplitOptions = MboxSplitOptions::default().max_file_size(Some(1));
+        let summary: MboxSplitSummary = split_mbox(folder.join("input.mbox"), &output, &options).unwrap();
+        assert_eq!(summary.files.len(), 3);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn places_unparsed_entries() {
+        // Quoted-printable bodies are decoded leniently, so no fixture entry fails to parse; the placement is checked directly
+        let splitting: MboxSplitOptions = MboxSplitOptions::default().split_by(Some(SplitBy::Month));
+        assert!(matches!(placement(None, &splitting, "input"), Placement::Unparsed(Some(group)) if group == UNPARSED_GROUP));
+        assert!(matches!(placement(None, &MboxSplitOptions::default(), "input"), Placement::Unparsed(Some(group)) if group == "input"));
+
+        let filtering: MboxSplitOptions = splitting.conversations(vec!["Space A".to_string()]);
+        assert!(matches!(placement(None, &filtering, "input"), Placement::Unparsed(None)));
+    }
+
+    #[test]
+    fn refuses_to_overwrite_the_input() {
+        let folder: PathBuf = test_folder("overwrite");
+        let input: PathBuf = fixture(&folder);
+        let original: Vec<u8> = fs::read(&input).unwrap();
+
+        let result: Result<MboxSplitSummary, ExtractorError> = split_mbox(&input, &folder, &MboxSplitOptions::default());
+        assert!(matches!(result, Err(ExtractorError::InvalidOption(_))));
+        assert_eq!(fs::read(&input).unwrap(), original);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
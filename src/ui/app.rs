@@ -8,11 +8,27 @@ use std::thread;
 use super::components::render_ui;
 use super::animations::update_animations;
 use super::processing::process_mbox;
+use gchat_mbox_extractor::{
+    AttachmentFilter, CsvFormat, DocumentUnit, ExportFormat, ExtractionOptions, LoadFileFormat, MessageColumn, EXCEL_MAX_ROWS,
+};
 
 pub enum Action {
     OpenMboxFileDialog,
     OpenOutputFolderDialog,
     ToggleExportAttachments,
+    ToggleExportFormat(ExportFormat),
+    ToggleContinueOnError,
+    ToggleDeduplicateAttachments,
+    SetAttachmentPathTemplate(String),
+    SetFilterText(FilterField, String),
+    ToggleCsvByteOrderMark,
+    ToggleCsvSplitting,
+    ToggleMarkdownByMonth,
+    SetBatesPrefix(String),
+    ToggleLoadFilePerMessage,
+    ToggleNeutralizeFormulas,
+    SetCsvDelimiter(String),
+    SetCsvColumns(String),
     StartProcessing,
     UpdateProgress(f32),
     FinishProcessing,
@@ -20,20 +36,125 @@ pub enum Action {
     UpdateButtonAnimation(usize, f32),
 }
 
+/// Identifies one of the attachment filter text fields
+#[derive(Clone, Copy)]
+pub enum FilterField {
+    AllowedTypes,
+    DeniedTypes,
+    AllowedExtensions,
+    DeniedExtensions,
+    MaxSizeMb,
+    IncludeNames,
+    ExcludeNames,
+}
+
+/// Holds the attachment filter text fields as typed, until they are parsed into an `AttachmentFilter`
+#[derive(Default)]
+pub struct FilterInput {
+    pub allowed_types: String,
+    pub denied_types: String,
+    pub allowed_extensions: String,
+    pub denied_extensions: String,
+    pub max_size_mb: String,
+    pub include_names: String,
+    pub exclude_names: String,
+}
+
+impl FilterInput {
+    /// Returns the text of a filter field
+    pub fn field(&self, field: FilterField) -> &String {
+        match field {
+            FilterField::AllowedTypes => &self.allowed_types,
+            FilterField::DeniedTypes => &self.denied_types,
+            FilterField::AllowedExtensions => &self.allowed_extensions,
+            FilterField::DeniedExtensions => &self.denied_extensions,
+            FilterField::MaxSizeMb => &self.max_size_mb,
+            FilterField::IncludeNames => &self.include_names,
+            FilterField::ExcludeNames => &self.exclude_names,
+        }
+    }
+
+    /// Returns the text of a filter field for editing
+    fn field_mut(&mut self, field: FilterField) -> &mut String {
+        match field {
+            FilterField::AllowedTypes => &mut self.allowed_types,
+            FilterField::DeniedTypes => &mut self.denied_types,
+            FilterField::AllowedExtensions => &mut self.allowed_extensions,
+            FilterField::DeniedExtensions => &mut self.denied_extensions,
+            FilterField::MaxSizeMb => &mut self.max_size_mb,
+            FilterField::IncludeNames => &mut self.include_names,
+            FilterField::ExcludeNames => &mut self.exclude_names,
+        }
+    }
+
+    /// Parses the text fields into an `AttachmentFilter`; an empty maximum size sets no limit and invalid name patterns are rejected
+    fn to_filter(&self) -> Result<AttachmentFilter, String> {
+        let max_size: Option<usize> = match self.max_size_mb.trim() {
+            "" => None,
+            text => match text.parse::<f64>() {
+                Ok(mb) if mb.is_finite() && mb >= 0.0 => Some((mb * 1024.0 * 1024.0) as usize),
+                _ => return Err(format!("The maximum attachment size must be a number of megabytes, got '{}'", text)),
+            },
+        };
+        AttachmentFilter::default()
+            .allowed_mime_types(AttachmentFilter::parse_list(&self.allowed_types))
+            .denied_mime_types(AttachmentFilter::parse_list(&self.denied_types))
+            .allowed_extensions(AttachmentFilter::parse_list(&self.allowed_extensions))
+            .denied_extensions(AttachmentFilter::parse_list(&self.denied_extensions))
+            .max_size(max_size)
+            .include_patterns(&AttachmentFilter::parse_list(&self.include_names))?
+            .exclude_patterns(&AttachmentFilter::parse_list(&self.exclude_names))
+    }
+}
+
+/// Holds the CSV format text fields as typed, until they are parsed into a `CsvFormat`
+pub struct CsvInput {
+    pub delimiter: String,
+    pub columns: String,
+}
+
+impl Default for CsvInput {
+    fn default() -> Self {
+        Self {
+            delimiter: ",".to_string(),
+            columns: MessageColumn::ALL.map(|column: MessageColumn| column.name()).join(", "),
+        }
+    }
+}
+
+impl CsvInput {
+    /// Applies the text fields to a `CsvFormat`; "tab" selects a tab delimiter
+    fn apply(&self, format: &CsvFormat) -> Result<CsvFormat, String> {
+        let delimiter: char = match self.delimiter.trim() {
+            "" => ',',
+            text if text.eq_ignore_ascii_case("tab") => '\t',
+            text if text.chars().count() == 1 => text.chars().next().unwrap_or(','),
+            text => return Err(format!("The CSV delimiter must be a single character, got '{}'", text)),
+        };
+        let columns: Vec<MessageColumn> = AttachmentFilter::parse_list(&self.columns).iter()
+            .map(|name: &String| name.parse::<MessageColumn>())
+            .collect::<Result<Vec<MessageColumn>, String>>()?;
+        let format: CsvFormat = format.clone().delimiter(delimiter).message_columns(columns);
+        format.validate()?;
+        Ok(format)
+    }
+}
+
 enum DialogType {
     File,
     Folder,
 }
 
 enum DialogMessage {
-    Open(DialogType),
     Result(DialogType, Option<PathBuf>),
 }
 
 /// Represents the main application state
 pub struct MboxExtractorApp {
     pub mbox_path: Option<PathBuf>,
-    pub export_attachments: bool,
+    pub options: ExtractionOptions,
+    pub filter_input: FilterInput,
+    pub csv_input: CsvInput,
     pub output_path: Option<PathBuf>,
     pub processing: bool,
     pub result: String,
@@ -59,7 +180,9 @@ impl MboxExtractorApp {
 
         Self {
             mbox_path: None,
-            export_attachments: false,
+            options: ExtractionOptions::default(),
+            filter_input: FilterInput::default(),
+            csv_input: CsvInput::default(),
             output_path: None,
             processing: false,
             result: String::new(),
@@ -85,7 +208,43 @@ impl MboxExtractorApp {
         match action {
             Action::OpenMboxFileDialog => self.open_file_dialog(),
             Action::OpenOutputFolderDialog => self.open_folder_dialog(),
-            Action::ToggleExportAttachments => self.export_attachments = !self.export_attachments,
+            Action::ToggleExportAttachments => self.options.export_attachments = !self.options.export_attachments,
+            Action::ToggleExportFormat(format) => {
+                let formats: &mut Vec<ExportFormat> = &mut self.options.export_formats;
+                match formats.iter().position(|selected: &ExportFormat| *selected == format) {
+                    Some(index) => { formats.remove(index); },
+                    None => formats.push(format),
+                }
+            },
+            Action::ToggleContinueOnError => self.options.continue_on_error = !self.options.continue_on_error,
+            Action::ToggleDeduplicateAttachments => {
+                self.options.deduplicate_attachments = !self.options.deduplicate_attachments;
+            },
+            Action::SetAttachmentPathTemplate(template) => self.options.attachment_path_template = template,
+            Action::SetFilterText(field, text) => *self.filter_input.field_mut(field) = text,
+            Action::ToggleCsvByteOrderMark => {
+                self.options.csv_format.byte_order_mark = !self.options.csv_format.byte_order_mark;
+            },
+            Action::ToggleCsvSplitting => {
+                let format: &mut CsvFormat = &mut self.options.csv_format;
+                format.max_rows_per_file = if format.max_rows_per_file.is_some() { None } else { Some(EXCEL_MAX_ROWS) };
+            },
+            Action::ToggleMarkdownByMonth => {
+                self.options.transcript_format.split_by_month = !self.options.transcript_format.split_by_month;
+            },
+            Action::SetBatesPrefix(prefix) => self.options.pdf_format.bates_prefix = prefix,
+            Action::ToggleLoadFilePerMessage => {
+                let format: &mut LoadFileFormat = &mut self.options.load_file_format;
+                format.unit = match format.unit {
+                    DocumentUnit::Conversation => DocumentUnit::Message,
+                    DocumentUnit::Message => DocumentUnit::Conversation,
+                };
+            },
+            Action::ToggleNeutralizeFormulas => {
+                self.options.csv_format.neutralize_formulas = !self.options.csv_format.neutralize_formulas;
+            },
+            Action::SetCsvDelimiter(delimiter) => self.csv_input.delimiter = delimiter,
+            Action::SetCsvColumns(columns) => self.csv_input.columns = columns,
             Action::StartProcessing => self.start_processing(),
             Action::UpdateProgress(progress) => {
                 self.progress = Some(progress);
@@ -132,6 +291,20 @@ impl MboxExtractorApp {
 
     /// Initializes MBOX processing
     fn start_processing(&mut self) {
+        let inputs: Result<(CsvFormat, AttachmentFilter), String> = self.csv_input.apply(&self.options.csv_format)
+            .and_then(|format: CsvFormat| Ok((format, self.filter_input.to_filter()?)));
+        match inputs {
+            Ok((format, filter)) => {
+                self.options.csv_format = format;
+                self.options.attachment_filter = filter;
+            }
+            Err(e) => {
+                self.result = format!("Error: {}", e);
+                self.result_start_time = Some(Instant::now());
+                self.result_animation = 0.0;
+                return;
+            }
+        }
         self.processing = true;
         self.result = String::new();
         self.progress = Some(0.0);
@@ -147,7 +320,7 @@ impl MboxExtractorApp {
         if self.processing {
             let mbox_path: Option<PathBuf> = self.mbox_path.clone();
             let output_path: Option<PathBuf> = self.output_path.clone();
-            let export_attachments: bool = self.export_attachments;
+            let options: ExtractionOptions = self.options.clone();
 
             if let (Some(mbox_path), Some(output_path)) = (mbox_path, output_path) {
                 let (progress_tx, progress_rx) = channel();
@@ -157,7 +330,7 @@ impl MboxExtractorApp {
                 self.result_rx = Some(result_rx);
 
                 thread::spawn(move || {
-                    process_mbox(&mbox_path, &output_path, export_attachments, progress_tx, result_tx);
+                    process_mbox(&mbox_path, &output_path, &options, progress_tx, result_tx);
                 });
             }
         }
@@ -189,7 +362,6 @@ impl MboxExtractorApp {
                     self.output_path = result;
                     self.current_dialog = None;
                 }
-                _ => {}
             }
         }
     }
@@ -239,8 +411,8 @@ impl eframe::App for MboxExtractorApp {
 pub fn run_ui() -> Result<(), eframe::Error> {
     let options = NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([400.0, 450.0])
-            .with_min_inner_size([400.0, 450.0])
+            .with_inner_size([400.0, 530.0])
+            .with_min_inner_size([400.0, 530.0])
             .with_resizable(false)
             .with_fullscreen(false)
             .with_maximize_button(false),
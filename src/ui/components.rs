@@ -1,12 +1,25 @@
-use egui::{Ui, RichText, Frame, Color32, Rounding, Stroke, Vec2, Rect, Sense, ProgressBar};
+use egui::{Ui, RichText, Frame, Color32, Rounding, Stroke, Vec2, Rect, Sense, ProgressBar, ScrollArea, CollapsingHeader, Grid};
 
-use super::app::{MboxExtractorApp, Action};
+use gchat_mbox_extractor::{DocumentUnit, ExportFormat};
+
+use super::app::{MboxExtractorApp, Action, FilterField};
 
 const BG_COLOR: Color32 = Color32::from_rgb(33, 37, 41);
 const ACCENT_COLOR: Color32 = Color32::from_rgb(72,77,83);
 const TEXT_COLOR: Color32 = Color32::from_rgb(211,211,212);
 const BUTTON_COLOR: Color32 = Color32::from_rgb(52,58,64);
 const HOVER_COLOR: Color32 = Color32::from_rgb(72,77,83);
+const TEMPLATE_HINT: &str = "Placeholders: {space} {yyyy} {mm} {dd} {sender} {message_id} {entry} {filename}";
+const COLUMNS_HINT: &str = "Comma-separated, in order: message_id, sender, timestamp, content";
+const FILTER_FIELDS: [(FilterField, &str, &str); 7] = [
+    (FilterField::AllowedTypes, "Allowed Types", "Comma-separated MIME types, e.g. image/*, application/pdf"),
+    (FilterField::DeniedTypes, "Blocked Types", "Comma-separated MIME types, e.g. video/*"),
+    (FilterField::AllowedExtensions, "Allowed Extensions", "Comma-separated extensions, e.g. pdf, docx"),
+    (FilterField::DeniedExtensions, "Blocked Extensions", "Comma-separated extensions, e.g. exe, msi"),
+    (FilterField::MaxSizeMb, "Max Size (MB)", "Larger attachments are listed in attachments.csv but not written"),
+    (FilterField::IncludeNames, "Include Names", "Comma-separated filename patterns, e.g. invoice_*.pdf"),
+    (FilterField::ExcludeNames, "Exclude Names", "Comma-separated filename patterns, e.g. *.tmp"),
+];
 
 /// Called to render UI elements
 pub fn render_ui(app: &MboxExtractorApp, ctx: &egui::Context) -> Vec<Action> {
@@ -17,7 +30,7 @@ pub fn render_ui(app: &MboxExtractorApp, ctx: &egui::Context) -> Vec<Action> {
         .inner_margin(20.0);
 
     egui::CentralPanel::default().frame(frame).show(ctx, |ui: &mut Ui| {
-        ui.vertical_centered(|ui: &mut Ui| {
+        ScrollArea::vertical().show(ui, |ui: &mut Ui| ui.vertical_centered(|ui: &mut Ui| {
             ui.add_space(20.0);
             ui.heading(RichText::new("GChat MBOX Extractor").color(TEXT_COLOR).size(24.0));
             ui.add_space(30.0);
@@ -26,6 +39,8 @@ pub fn render_ui(app: &MboxExtractorApp, ctx: &egui::Context) -> Vec<Action> {
                 actions.extend(render_file_selection(app, ui));
                 ui.add_space(20.0);
                 actions.extend(render_output_selection(app, ui));
+                actions.extend(render_export_formats(app, ui));
+                actions.extend(render_csv_format(app, ui));
                 ui.add_space(20.0);
                 actions.extend(render_process_button(app, ui));
             } else {
@@ -34,7 +49,7 @@ pub fn render_ui(app: &MboxExtractorApp, ctx: &egui::Context) -> Vec<Action> {
 
             ui.add_space(30.0);
             render_result(app, ui);
-        });
+        }));
     });
 
     actions
@@ -71,10 +86,120 @@ fn render_output_selection(app: &MboxExtractorApp, ui: &mut Ui) -> Vec<Action> {
             ui.label(RichText::new(format!("Folder: {}", path.file_name().unwrap_or_default().to_string_lossy())).color(TEXT_COLOR));
         }
         ui.add_space(10.0);
-        let mut export_attachments: bool = app.export_attachments;
+        let mut export_attachments: bool = app.options.export_attachments;
         if ui.checkbox(&mut export_attachments, RichText::new("Export Attachments").color(TEXT_COLOR)).changed() {
             actions.push(Action::ToggleExportAttachments);
         }
+        if app.options.export_attachments {
+            let mut continue_on_error: bool = app.options.continue_on_error;
+            if ui.checkbox(&mut continue_on_error, RichText::new("Skip Failed Attachments").color(TEXT_COLOR)).changed() {
+                actions.push(Action::ToggleContinueOnError);
+            }
+            let mut deduplicate_attachments: bool = app.options.deduplicate_attachments;
+            if ui.checkbox(&mut deduplicate_attachments, RichText::new("Deduplicate Attachments").color(TEXT_COLOR)).changed() {
+                actions.push(Action::ToggleDeduplicateAttachments);
+            }
+            if !app.options.deduplicate_attachments {
+                let mut template: String = app.options.attachment_path_template.clone();
+                ui.horizontal(|ui: &mut Ui| {
+                    ui.label(RichText::new("Folder Layout").color(TEXT_COLOR));
+                    if ui.text_edit_singleline(&mut template).on_hover_text(TEMPLATE_HINT).changed() {
+                        actions.push(Action::SetAttachmentPathTemplate(template));
+                    }
+                });
+            }
+            actions.extend(render_attachment_filters(app, ui));
+        }
+    });
+    actions
+}
+
+/// Renders the collapsible attachment filter fields.
+fn render_attachment_filters(app: &MboxExtractorApp, ui: &mut Ui) -> Vec<Action> {
+    let mut actions: Vec<Action> = Vec::new();
+    CollapsingHeader::new(RichText::new("Attachment Filters").color(TEXT_COLOR)).show(ui, |ui: &mut Ui| {
+        Grid::new("attachment_filters").num_columns(2).show(ui, |ui: &mut Ui| {
+            for (field, label, hint) in FILTER_FIELDS {
+                ui.label(RichText::new(label).color(TEXT_COLOR));
+                let mut text: String = app.filter_input.field(field).clone();
+                if ui.text_edit_singleline(&mut text).on_hover_text(hint).changed() {
+                    actions.push(Action::SetFilterText(field, text));
+                }
+                ui.end_row();
+            }
+        });
+    });
+    actions
+}
+
+/// Renders the collapsible CSV format fields.
+fn render_csv_format(app: &MboxExtractorApp, ui: &mut Ui) -> Vec<Action> {
+    let mut actions: Vec<Action> = Vec::new();
+    CollapsingHeader::new(RichText::new("CSV Format").color(TEXT_COLOR)).show(ui, |ui: &mut Ui| {
+        Grid::new("csv_format").num_columns(2).show(ui, |ui: &mut Ui| {
+            ui.label(RichText::new("Delimiter").color(TEXT_COLOR));
+            let mut delimiter: String = app.csv_input.delimiter.clone();
+            if ui.text_edit_singleline(&mut delimiter).on_hover_text("A single character, or \"tab\"").changed() {
+                actions.push(Action::SetCsvDelimiter(delimiter));
+            }
+            ui.end_row();
+
+            ui.label(RichText::new("Columns").color(TEXT_COLOR));
+            let mut columns: String = app.csv_input.columns.clone();
+            if ui.text_edit_singleline(&mut columns).on_hover_text(COLUMNS_HINT).changed() {
+                actions.push(Action::SetCsvColumns(columns));
+            }
+            ui.end_row();
+        });
+        let mut byte_order_mark: bool = app.options.csv_format.byte_order_mark;
+        if ui.checkbox(&mut byte_order_mark, RichText::new("Excel Compatible (UTF-8 BOM)").color(TEXT_COLOR)).changed() {
+            actions.push(Action::ToggleCsvByteOrderMark);
+        }
+        let mut split: bool = app.options.csv_format.max_rows_per_file.is_some();
+        if ui.checkbox(&mut split, RichText::new("Split at Excel Row Limit").color(TEXT_COLOR)).changed() {
+            actions.push(Action::ToggleCsvSplitting);
+        }
+        let mut neutralize_formulas: bool = app.options.csv_format.neutralize_formulas;
+        if ui.checkbox(&mut neutralize_formulas, RichText::new("Neutralize Formulas").color(TEXT_COLOR))
+            .on_hover_text("Prefixes cells starting with =, +, - or @ with ' so spreadsheets do not run them")
+            .changed() {
+            actions.push(Action::ToggleNeutralizeFormulas);
+        }
+    });
+    actions
+}
+
+/// Renders the collapsible checkboxes of the additional export formats included in this build.
+fn render_export_formats(app: &MboxExtractorApp, ui: &mut Ui) -> Vec<Action> {
+    let mut actions: Vec<Action> = Vec::new();
+    CollapsingHeader::new(RichText::new("Additional Formats").color(TEXT_COLOR)).show(ui, |ui: &mut Ui| {
+        for format in ExportFormat::ALL.into_iter().filter(ExportFormat::is_available) {
+            let mut selected: bool = app.options.export_formats.contains(&format);
+            if ui.checkbox(&mut selected, RichText::new(format.label()).color(TEXT_COLOR)).changed() {
+                actions.push(Action::ToggleExportFormat(format));
+            }
+        }
+        if app.options.export_formats.contains(&ExportFormat::Markdown) {
+            let mut by_month: bool = app.options.transcript_format.split_by_month;
+            if ui.checkbox(&mut by_month, RichText::new("One Markdown File per Month").color(TEXT_COLOR)).changed() {
+                actions.push(Action::ToggleMarkdownByMonth);
+            }
+        }
+        if app.options.export_formats.contains(&ExportFormat::Pdf) {
+            ui.horizontal(|ui: &mut Ui| {
+                ui.label(RichText::new("Bates Prefix").color(TEXT_COLOR));
+                let mut prefix: String = app.options.pdf_format.bates_prefix.clone();
+                if ui.text_edit_singleline(&mut prefix).on_hover_text("e.g. ACME, giving ACME000001, ACME000002, ...").changed() {
+                    actions.push(Action::SetBatesPrefix(prefix));
+                }
+            });
+        }
+        if app.options.export_formats.contains(&ExportFormat::LoadFile) {
+            let mut per_message: bool = app.options.load_file_format.unit == DocumentUnit::Message;
+            if ui.checkbox(&mut per_message, RichText::new("One Load File Document per Message").color(TEXT_COLOR)).changed() {
+                actions.push(Action::ToggleLoadFilePerMessage);
+            }
+        }
     });
     actions
 }
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, TimestampMicrosecondArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use regex::Regex;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, Message};
+
+/// Returns the schema of the message tables (see `docs/parquet-schema.md`).
+pub fn message_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("entry_index", DataType::UInt64, false),
+        Field::new("message_id", DataType::Utf8, false),
+        Field::new("space", DataType::Utf8, false),
+        Field::new("sender", DataType::Utf8, false),
+        Field::new("sent_at", DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), true),
+        Field::new("timestamp", DataType::Utf8, false),
+        Field::new("content", DataType::Utf8, false),
+        Field::new("mentions", string_list(), false),
+    ]))
+}
+
+/// Returns the schema of the attachment tables (see `docs/parquet-schema.md`).
+pub fn attachment_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("entry_index", DataType::UInt64, false),
+        Field::new("message_id", DataType::Utf8, false),
+        Field::new("original_filename", DataType::Utf8, false),
+        Field::new("stored_path", DataType::Utf8, false),
+        Field::new("declared_content_type", DataType::Utf8, false),
+        Field::new("sniffed_content_type", DataType::Utf8, false),
+        Field::new("size", DataType::UInt64, false),
+        Field::new("sha256", DataType::Utf8, false),
+        Field::new("type_mismatch", DataType::Boolean, false),
+        Field::new("duplicate", DataType::Boolean, false),
+        Field::new("filter_reason", DataType::Utf8, false),
+    ]))
+}
+
+/// Converts messages into a record batch following `message_schema`.
+///
+/// # Arguments
+///
+/// * `messages` - The messages to convert, each with the index and the space of its entry.
+///
+/// # Returns
+///
+/// A Result containing the record batch, or an error if the columns do not match the schema.
+pub fn messages_to_record_batch(messages: &[(usize, String, Message)]) -> Result<RecordBatch, ExtractorError> {
+    let mention_regex: Regex = Regex::new(r"(?:^|\s)@([^\s@]+)").unwrap();
+    let mut mentions: ListBuilder<StringBuilder> = ListBuilder::new(StringBuilder::new());
+    for (_, _, message) in messages {
+        for capture in mention_regex.captures_iter(&message.content) {
+            mentions.values().append_value(capture[1].trim_end_matches(['.', ',', ';', ':', '!', '?', ')']));
+        }
+        mentions.append(true);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(messages.iter().map(|(index, _, _)| *index as u64))),
+        Arc::new(StringArray::from_iter_values(messages.iter().map(|(_, _, message)| &message.message_id))),
+        Arc::new(StringArray::from_iter_values(messages.iter().map(|(_, space, _)| space))),
+        Arc::new(StringArray::from_iter_values(messages.iter().map(|(_, _, message)| &message.sender))),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter(
+                messages.iter().map(|(_, _, message)| message.datetime().map(|datetime| datetime.timestamp_micros())),
+            )
+            .with_timezone("UTC"),
+        ),
+        Arc::new(StringArray::from_iter_values(messages.iter().map(|(_, _, message)| &message.timestamp))),
+        Arc::new(StringArray::from_iter_values(messages.iter().map(|(_, _, message)| &message.content))),
+        Arc::new(mentions.finish()),
+    ];
+    Ok(RecordBatch::try_new(message_schema(), columns)?)
+}
+
+/// Converts attachment records into a record batch following `attachment_schema`.
+///
+/// # Arguments
+///
+/// * `records` - The attachment records to convert.
+///
+/// # Returns
+///
+/// A Result containing the record batch, or an error if the columns do not match the schema.
+pub fn attachments_to_record_batch(records: &[AttachmentRecord]) -> Result<RecordBatch, ExtractorError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(records.iter().map(|record: &AttachmentRecord| record.entry_index as u64))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record: &AttachmentRecord| &record.message_id))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record: &AttachmentRecord| &record.original_filename))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record: &AttachmentRecord| &record.stored_path))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record: &AttachmentRecord| &record.declared_content_type))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record: &AttachmentRecord| &record.sniffed_content_type))),
+        Arc::new(UInt64Array::from_iter_values(records.iter().map(|record: &AttachmentRecord| record.size as u64))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record: &AttachmentRecord| &record.sha256))),
+        Arc::new(BooleanArray::from_iter(records.iter().map(|record: &AttachmentRecord| Some(record.type_mismatch)))),
+        Arc::new(BooleanArray::from_iter(records.iter().map(|record: &AttachmentRecord| Some(record.duplicate)))),
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record: &AttachmentRecord| &record.filter_reason))),
+    ];
+    Ok(RecordBatch::try_new(attachment_schema(), columns)?)
+}
+
+/// Helper function for the type of a list of strings, as built by `ListBuilder<StringBuilder>`
+fn string_list() -> DataType {
+    DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+}
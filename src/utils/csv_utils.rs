@@ -1,14 +1,25 @@
-use std::error::Error;
 use std::fs::File;
-use std::io::Write;
-use crate::models::Message;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, CsvFormat, Message, MessageColumn, QuoteStyle};
+
+/// The UTF-8 byte order mark, which Excel needs to open a CSV file as UTF-8.
+const BYTE_ORDER_MARK: &str = "\u{feff}";
+
+/// The characters that make spreadsheet applications evaluate a cell as a formula.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
 
 /// Writes a collection of Message structs to a CSV file with progress updates.
 ///
+/// When the format sets a row limit and the messages do not fit in a single file, they are rolled into
+/// numbered files instead (`messages_1.csv`, `messages_2.csv`, ...), each starting with the header row.
+///
 /// # Arguments
 ///
 /// * `messages` - A slice of Message structs to be written to the CSV file.
 /// * `filename` - A string slice specifying the name of the output CSV file.
+/// * `format` - The dialect, columns, row limit and formula handling of the CSV file.
 /// * `progress_callback` - A closure that will be called with progress updates.
 ///
 /// # Returns
@@ -17,40 +28,323 @@ use crate::models::Message;
 ///
 /// # Errors
 ///
-/// This function will return an error if creating the file or writing to it fails.
+/// This function will return an error if the format is invalid, or if creating a file or writing to it fails.
 pub fn write_messages_to_csv<F>(
     messages: &[Message],
     filename: &str,
+    format: &CsvFormat,
     mut progress_callback: F
-) -> Result<(), Box<dyn Error>>
+) -> Result<(), ExtractorError>
 where
-    F: FnMut(f32) -> Result<(), Box<dyn Error>>
+    F: FnMut(f32) -> Result<(), ExtractorError>
 {
-    let mut file: File = File::create(filename)?;
-
-    // Write CSV header
-    writeln!(file, "message_id,sender,timestamp,content")?;
+    format.validate().map_err(ExtractorError::InvalidOption)?;
 
+    let header: Vec<&str> = format.message_columns.iter().map(MessageColumn::name).collect();
+    let rows_per_file: usize = format.max_rows_per_file.map_or(usize::MAX, |max_rows: usize| max_rows - 1);
     let total_messages: usize = messages.len();
 
+    if total_messages <= rows_per_file {
+        return write_message_rows(messages, filename, format, &header, 0, total_messages, &mut progress_callback);
+    }
+
+    for (part, chunk) in messages.chunks(rows_per_file).enumerate() {
+        let part_filename: String = numbered_filename(filename, part + 1);
+        write_message_rows(chunk, &part_filename, format, &header, part * rows_per_file, total_messages, &mut progress_callback)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a header row and one row per message to a single CSV file, reporting progress over all messages
+fn write_message_rows<F>(
+    messages: &[Message],
+    filename: &str,
+    format: &CsvFormat,
+    header: &[&str],
+    first_index: usize,
+    total_messages: usize,
+    progress_callback: &mut F,
+) -> Result<(), ExtractorError>
+where
+    F: FnMut(f32) -> Result<(), ExtractorError>
+{
+    let mut writer: CsvWriter = CsvWriter::create(filename, format)?;
+
+    // Write CSV header
+    writer.write_record(header)?;
+
     // Write each message as a CSV row
-    for (index, message) in messages.iter().enumerate() {
-        let content: String = message.content.replace("\"", "\"\"");
-        writeln!(
-            file,
-            "\"{}\",\"{}\",\"{}\",\"{}\"",
-            message.message_id,
-            message.sender,
-            message.timestamp,
-            content
-        )?;
+    for (offset, message) in messages.iter().enumerate() {
+        let row: Vec<&str> = format.message_columns.iter().map(|column: &MessageColumn| column.value(message)).collect();
+        writer.write_record(&row)?;
 
         // Call the progress callback every 100 messages or on the last message
-        if index % 100 == 0 || index == total_messages - 1 {
+        let index: usize = first_index + offset;
+        if index.is_multiple_of(100) || index == total_messages - 1 {
             let progress = (index + 1) as f32 / total_messages as f32;
             progress_callback(progress)?;
         }
     }
 
-    Ok(())
-}
\ No newline at end of file
+    writer.finish()
+}
+
+/// Inserts a part number before the extension of a filename, e.g. `messages.csv` becomes `messages_2.csv`
+fn numbered_filename(filename: &str, part: usize) -> String {
+    let path: &Path = Path::new(filename);
+    let stem: String = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let numbered: String = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, part, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, part),
+    };
+    path.with_file_name(numbered).to_string_lossy().to_string()
+}
+
+/// Writes the attachment manifest, listing every attachment with its hashes, sizes and MIME types.
+///
+/// # Arguments
+///
+/// * `attachments` - A slice of AttachmentRecord structs to be written to the CSV file.
+/// * `filename` - A string slice specifying the name of the output CSV file.
+/// * `format` - The dialect of the CSV file; its message columns are ignored.
+///
+/// # Returns
+///
+/// A Result indicating success (Ok(())) or failure (Err) in writing the CSV file.
+///
+/// # Errors
+///
+/// This function will return an error if the format is invalid, or if creating the file or writing to it fails.
+pub fn write_attachments_to_csv(attachments: &[AttachmentRecord], filename: &str, format: &CsvFormat) -> Result<(), ExtractorError> {
+    let mut writer: CsvWriter = CsvWriter::create(filename, format)?;
+
+    writer.write_record(&[
+        "message_id", "entry_index", "original_filename", "stored_path", "declared_content_type",
+        "sniffed_content_type", "size", "sha256", "type_mismatch", "duplicate", "filter_reason",
+    ])?;
+
+    for attachment in attachments {
+        writer.write_record(&[
+            &attachment.message_id,
+            &attachment.entry_index.to_string(),
+            &attachment.original_filename,
+            &attachment.stored_path,
+            &attachment.declared_content_type,
+            &attachment.sniffed_content_type,
+            &attachment.size.to_string(),
+            &attachment.sha256,
+            &attachment.type_mismatch.to_string(),
+            &attachment.duplicate.to_string(),
+            &attachment.filter_reason,
+        ])?;
+    }
+
+    writer.finish()
+}
+
+/// Writes RFC 4180 records in a configurable dialect.
+struct CsvWriter<'a> {
+    out: BufWriter<File>,
+    format: &'a CsvFormat,
+}
+
+impl<'a> CsvWriter<'a> {
+    /// Creates the file, writing the byte order mark if the format asks for one.
+    fn create(filename: &str, format: &'a CsvFormat) -> Result<Self, ExtractorError> {
+        format.validate().map_err(ExtractorError::InvalidOption)?;
+
+        let mut out: BufWriter<File> = BufWriter::new(File::create(filename)?);
+        if format.byte_order_mark {
+            out.write_all(BYTE_ORDER_MARK.as_bytes())?;
+        }
+        Ok(Self { out, format })
+    }
+
+    /// Writes a record, escaping every field.
+    fn write_record<S: AsRef<str>>(&mut self, fields: &[S]) -> Result<(), ExtractorError> {
+        let mut record: String = String::new();
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                record.push(self.format.delimiter);
+            }
+            record.push_str(&escape_field(field.as_ref(), self.format));
+        }
+        record.push_str(self.format.line_ending.as_str());
+        self.out.write_all(record.as_bytes())?;
+        Ok(())
+    }
+
+    /// Flushes the buffered records to the file.
+    fn finish(mut self) -> Result<(), ExtractorError> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Encloses a field in quotes when needed, doubling any quotes it contains, and
+/// prefixes formula-leading fields with `'` when the format neutralizes formulas.
+fn escape_field(field: &str, format: &CsvFormat) -> String {
+    let neutralized: String;
+    let field: &str = if format.neutralize_formulas && field.starts_with(FORMULA_PREFIXES) {
+        neutralized = format!("'{}", field);
+        &neutralized
+    } else {
+        field
+    };
+
+    let quote: char = format.quote;
+    let needs_quotes: bool = format.quote_style == QuoteStyle::Always
+        || field.contains([format.delimiter, quote, '\r', '\n']);
+    if !needs_quotes {
+        return field.to_string();
+    }
+    let doubled: String = field.replace(quote, &format!("{}{}", quote, quote));
+    format!("{}{}{}", quote, doubled, quote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::models::LineEnding;
+
+    /// Returns a fresh path for a test file below the system temporary folder.
+    fn test_file(name: &str) -> PathBuf {
+        let path: PathBuf = std::env::temp_dir().join(format!("gchat_mbox_extractor_{}_{}.csv", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    /// Writes records with a `CsvWriter` and returns the bytes of the file.
+    fn written_records(name: &str, format: &CsvFormat, records: &[&[&str]]) -> Vec<u8> {
+        let path: PathBuf = test_file(name);
+        let mut writer: CsvWriter = CsvWriter::create(path.to_str().unwrap(), format).unwrap();
+        for record in records {
+            writer.write_record(record).unwrap();
+        }
+        writer.finish().unwrap();
+        let bytes: Vec<u8> = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    fn message(message_id: &str, sender: &str, timestamp: &str, content: &str) -> Message {
+        Message {
+            message_id: message_id.to_string(),
+            sender: sender.to_string(),
+            timestamp: timestamp.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn quotes_every_field_by_default() {
+        let bytes: Vec<u8> = written_records("always", &CsvFormat::default(), &[&["a", "say \"hi\"", ""]]);
+        assert_eq!(bytes, b"\"a\",\"say \"\"hi\"\"\",\"\"\r\n");
+    }
+
+    #[test]
+    fn quotes_only_when_necessary() {
+        let format: CsvFormat = CsvFormat::default().quote_style(QuoteStyle::Necessary);
+        let bytes: Vec<u8> = written_records("necessary", &format, &[&["plain", "a,b", "say \"hi\"", "two\r\nlines", "lone\rcr"]]);
+        assert_eq!(bytes, b"plain,\"a,b\",\"say \"\"hi\"\"\",\"two\r\nlines\",\"lone\rcr\"\r\n");
+    }
+
+    #[test]
+    fn uses_custom_delimiter_and_quote() {
+        let format: CsvFormat = CsvFormat::default()
+            .delimiter(';')
+            .quote('\'')
+            .quote_style(QuoteStyle::Necessary);
+        let bytes: Vec<u8> = written_records("dialect", &format, &[&["a;b", "it's", "a,b", "say \"hi\""]]);
+        assert_eq!(bytes, b"'a;b';'it''s';a,b;say \"hi\"\r\n");
+
+        let format: CsvFormat = format.delimiter('\t');
+        let bytes: Vec<u8> = written_records("tab", &format, &[&["a\tb", "c"]]);
+        assert_eq!(bytes, b"'a\tb'\tc\r\n");
+    }
+
+    #[test]
+    fn ends_records_with_the_line_ending() {
+        let records: &[&[&str]] = &[&["a", "b"], &["c", "d"]];
+        let crlf: Vec<u8> = written_records("crlf", &CsvFormat::default().quote_style(QuoteStyle::Necessary), records);
+        assert_eq!(crlf, b"a,b\r\nc,d\r\n");
+
+        let format: CsvFormat = CsvFormat::default().quote_style(QuoteStyle::Necessary).line_ending(LineEnding::Lf);
+        let lf: Vec<u8> = written_records("lf", &format, records);
+        assert_eq!(lf, b"a,b\nc,d\n");
+    }
+
+    #[test]
+    fn writes_the_byte_order_mark_once() {
+        let format: CsvFormat = CsvFormat::default().byte_order_mark(true).quote_style(QuoteStyle::Necessary);
+        let bytes: Vec<u8> = written_records("bom", &format, &[&["é"], &["x"]]);
+        assert_eq!(bytes, "\u{feff}é\r\nx\r\n".as_bytes());
+        assert!(!written_records("no_bom", &CsvFormat::default(), &[&["x"]]).starts_with(BYTE_ORDER_MARK.as_bytes()));
+    }
+
+    #[test]
+    fn writes_the_selected_message_columns_in_order() {
+        let path: PathBuf = test_file("columns");
+        let messages: Vec<Message> = vec![
+            message("1", "Jane \"JD\" Doe", "2024-01-01T00:00:00+00:00", "hello"),
+            message("2", "Doe, John", "2024-01-02T00:00:00+00:00", "two\nlines"),
+        ];
+        let format: CsvFormat = CsvFormat::default()
+            .quote_style(QuoteStyle::Necessary)
+            .line_ending(LineEnding::Lf)
+            .message_columns(vec![MessageColumn::Content, MessageColumn::Sender, MessageColumn::MessageId]);
+
+        write_messages_to_csv(&messages, path.to_str().unwrap(), &format, |_| Ok(())).unwrap();
+        let bytes: Vec<u8> = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(bytes, b"content,sender,message_id\nhello,\"Jane \"\"JD\"\" Doe\",1\n\"two\nlines\",\"Doe, John\",2\n");
+    }
+
+    #[test]
+    fn rejects_ambiguous_dialects() {
+        let path: PathBuf = test_file("ambiguous");
+        let format: CsvFormat = CsvFormat::default().delimiter('"');
+        assert!(write_messages_to_csv(&[], path.to_str().unwrap(), &format, |_| Ok(())).is_err());
+        assert!(!path.exists());
+    }
+
+    fn neutralizing_format() -> CsvFormat {
+        CsvFormat::default().quote_style(QuoteStyle::Necessary).neutralize_formulas(true)
+    }
+
+    #[test]
+    fn neutralizes_formula_leading_fields() {
+        let format: CsvFormat = neutralizing_format();
+        assert_eq!(escape_field("=1+1", &format), "'=1+1");
+        assert_eq!(escape_field("+33 6 12", &format), "'+33 6 12");
+        assert_eq!(escape_field("-2", &format), "'-2");
+        assert_eq!(escape_field("@SUM(A1:A9)", &format), "'@SUM(A1:A9)");
+        assert_eq!(escape_field("\t=cmd", &format), "'\t=cmd");
+    }
+
+    #[test]
+    fn neutralizes_before_quoting() {
+        let format: CsvFormat = neutralizing_format();
+        assert_eq!(escape_field("=HYPERLINK(\"http://x\",\"y\")", &format), "\"'=HYPERLINK(\"\"http://x\"\",\"\"y\"\")\"");
+        assert_eq!(escape_field("\r=1", &format), "\"'\r=1\"");
+    }
+
+    #[test]
+    fn leaves_other_fields_alone() {
+        let format: CsvFormat = neutralizing_format();
+        assert_eq!(escape_field("hello = world", &format), "hello = world");
+        assert_eq!(escape_field("", &format), "");
+        assert_eq!(escape_field("a,b", &format), "\"a,b\"");
+    }
+
+    #[test]
+    fn keeps_formulas_when_not_neutralizing() {
+        let format: CsvFormat = CsvFormat::default().quote_style(QuoteStyle::Necessary);
+        assert_eq!(escape_field("=1+1", &format), "=1+1");
+        assert_eq!(escape_field("@user", &format), "@user");
+    }
+}
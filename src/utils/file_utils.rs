@@ -1,9 +1,13 @@
+use std::fmt::Write as _;
 use std::fs;
-use std::fs::File;
-use std::io::Read;
-use std::path::{Path, PathBuf};
-use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
 use base64::{prelude::BASE64_STANDARD, Engine as _};
+use sha2::{Digest, Sha256};
+use chrono::{DateTime, FixedOffset};
+use filetime::FileTime;
+use crate::error::ExtractorError;
 use crate::models::Attachment;
 
 /// Reads the content of an .mbox file into a String.
@@ -14,57 +18,400 @@ use crate::models::Attachment;
 ///
 /// # Returns
 ///
-/// A Result containing the file content as a String if successful, or a boxed error if reading fails.
+/// A Result containing the file content as a String if successful, or an `ExtractorError` if reading fails.
 ///
 /// # Errors
 ///
 /// This function will return an error if the file cannot be opened or read.
-pub fn read_mbox_file<P: AsRef<Path>>(path: P) -> Result<String, Box<dyn Error>> {
+pub fn read_mbox_file<P: AsRef<Path>>(path: P) -> Result<String, ExtractorError> {
     let mut file: File = File::open(path)?;
     let mut content: String = String::new();
     file.read_to_string(&mut content)?;
     Ok(content)
 }
 
-/// Writes an attachment to a file in the specified folder.
+/// The maximum length, in bytes, of a sanitized attachment filename.
+const MAX_FILENAME_LENGTH: usize = 200;
+
+/// The maximum length, in bytes and including the dot, of an extension kept when truncating a filename.
+const MAX_EXTENSION_LENGTH: usize = 32;
+
+/// Device names that cannot be used as filenames on Windows, regardless of extension.
+const RESERVED_WINDOWS_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turns an untrusted attachment filename into a safe, single-component filename.
+///
+/// Any directory part is dropped, control and reserved characters are replaced with `_`,
+/// leading/trailing dots and spaces are trimmed, reserved Windows device names are prefixed
+/// with `_` and overlong names are truncated while keeping their extension, unless the extension is overlong itself.
+///
+/// # Arguments
+///
+/// * `filename` - A string slice containing the filename as declared in the .mbox file.
+///
+/// # Returns
+///
+/// A non-empty String that is safe to join onto an output folder.
+pub fn sanitize_filename(filename: &str) -> String {
+    let base_name: &str = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let replaced: String = base_name.chars()
+        .map(|c: char| if c.is_control() || "<>:\"|?*".contains(c) { '_' } else { c })
+        .collect();
+    let mut sanitized: String = replaced.trim_matches(|c: char| c == '.' || c.is_whitespace()).to_string();
+
+    if sanitized.is_empty() {
+        sanitized = "attachment".to_string();
+    }
+
+    let device_name: String = sanitized.split('.').next().unwrap_or_default().to_ascii_uppercase();
+    if RESERVED_WINDOWS_NAMES.contains(&device_name.as_str()) {
+        sanitized = format!("_{}", sanitized);
+    }
+
+    if sanitized.len() > MAX_FILENAME_LENGTH {
+        // An overlong extension is no real one, so it is truncated along with the rest of the name
+        let (stem, extension) = match split_extension(&sanitized) {
+            (stem, Some(extension)) if extension.len() < MAX_EXTENSION_LENGTH => (stem, format!(".{}", extension)),
+            _ => (sanitized.as_str(), String::new()),
+        };
+        let mut stem_length: usize = (MAX_FILENAME_LENGTH - extension.len()).min(stem.len());
+        while !stem.is_char_boundary(stem_length) {
+            stem_length -= 1;
+        }
+        let stem: &str = stem[..stem_length].trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+        sanitized = format!("{}{}", stem, extension);
+    }
+
+    sanitized
+}
+
+/// Splits a filename into its stem and (optional) extension.
+fn split_extension(filename: &str) -> (&str, Option<&str>) {
+    match filename.rfind('.') {
+        Some(index) if index > 0 && index < filename.len() - 1 => (&filename[..index], Some(&filename[index + 1..])),
+        _ => (filename, None),
+    }
+}
+
+/// Builds a path inside `folder` that does not exist yet, appending `(2)`, `(3)`, ... to the stem on collision.
+fn unique_file_path(folder: &Path, filename: &str) -> PathBuf {
+    let mut file_path: PathBuf = folder.join(filename);
+    let (stem, extension) = split_extension(filename);
+    let mut counter: u32 = 1;
+
+    while file_path.exists() {
+        counter += 1;
+        let new_filename: String = match extension {
+            Some(extension) => format!("{}({}).{}", stem, counter, extension),
+            None => format!("{}({})", stem, counter),
+        };
+        file_path = folder.join(new_filename);
+    }
+
+    file_path
+}
+
+/// Decodes the base64 content of an attachment.
 ///
 /// # Arguments
 ///
 /// * `attachment` - A reference to an Attachment struct containing the attachment data.
+///
+/// # Returns
+///
+/// A Result containing the decoded bytes of the attachment.
+///
+/// # Errors
+///
+/// This function will return an error if the content is not valid base64.
+pub fn decode_attachment(attachment: &Attachment) -> Result<Vec<u8>, ExtractorError> {
+    Ok(BASE64_STANDARD.decode(&attachment.content)?)
+}
+
+/// Computes the lowercase hexadecimal SHA-256 digest of some content.
+pub fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Detects the MIME type of some content from its magic bytes.
+///
+/// # Arguments
+///
+/// * `content` - The decoded content of an attachment.
+///
+/// # Returns
+///
+/// An Option containing the detected MIME type, or None if the content has no known signature.
+pub fn sniff_content_type(content: &[u8]) -> Option<String> {
+    infer::get(content).map(|kind: infer::Type| kind.mime_type().to_string())
+}
+
+/// Writes an attachment to a file in the specified folder.
+///
+/// The relative path is expected to be sanitized already (see `sanitize_filename` and
+/// `render_attachment_path`); it is nevertheless verified to stay inside `folder`,
+/// so a hostile filename can never write elsewhere.
+///
+/// # Arguments
+///
+/// * `relative_path` - The path of the attachment file relative to `folder`, possibly including subfolders.
+/// * `content` - The decoded content of the attachment (see `decode_attachment`).
 /// * `folder` - A string slice specifying the folder where the attachment should be saved.
 ///
 /// # Returns
 ///
-/// A Result indicating success (Ok(())) or failure (Err) in writing the attachment.
+/// A Result containing the path of the written file.
 ///
 /// # Errors
 ///
-/// This function will return an error if creating the folder,
-/// decoding the content, or writing the file fails.
-pub fn write_attachment_to_file(attachment: &Attachment, folder: &str) -> Result<(), Box<dyn Error>> {
+/// This function will return an error if creating the folders or writing
+/// the file fails, or if the resolved path would escape the folder.
+pub fn write_attachment_to_file(relative_path: &Path, content: &[u8], folder: &str) -> Result<PathBuf, ExtractorError> {
+    let folder_path: &Path = Path::new(folder);
+    if !relative_path.components().all(|c: Component| matches!(c, Component::Normal(_))) {
+        return Err(ExtractorError::InvalidPath(relative_path.to_path_buf()));
+    }
+
+    let file_path: PathBuf = folder_path.join(relative_path);
+    let parent_path: &Path = file_path.parent().unwrap_or(folder_path);
+    if !parent_path.exists() {
+        fs::create_dir_all(parent_path)?;
+    }
+
+    let filename: String = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let file_path: PathBuf = unique_file_path(parent_path, &filename);
+    ensure_within_folder(folder_path, &file_path)?;
+
+    let mut file: File = OpenOptions::new().write(true).create_new(true).open(&file_path)?;
+    file.write_all(content)?;
+
+    Ok(file_path)
+}
+
+/// Writes an attachment to the specified folder under its content hash.
+///
+/// The file is named `<sha256>.<extension>`, with the extension taken from the sanitized attachment filename.
+/// Deduplicating is up to the caller, which writes the first attachment with a given hash and reuses the returned
+/// path for every later one, whatever its name. A file of the same name left by an earlier run holds the same
+/// content and is overwritten.
+///
+/// # Arguments
+///
+/// * `attachment` - A reference to an Attachment struct describing the attachment.
+/// * `content` - The decoded content of the attachment (see `decode_attachment`).
+/// * `sha256` - The hexadecimal SHA-256 digest of `content` (see `sha256_hex`).
+/// * `folder` - A string slice specifying the folder where the attachment should be saved.
+///
+/// # Returns
+///
+/// A Result containing the path of the stored file.
+///
+/// # Errors
+///
+/// This function will return an error if creating the folder or writing
+/// the file fails, or if the resolved path would escape the folder.
+pub fn write_attachment_blob(
+    attachment: &Attachment,
+    content: &[u8],
+    sha256: &str,
+    folder: &str,
+) -> Result<PathBuf, ExtractorError> {
     let folder_path: &Path = Path::new(folder);
     if !folder_path.exists() {
         fs::create_dir_all(folder_path)?;
     }
 
-    let original_filename: &String = &attachment.filename;
-    let mut file_path: PathBuf = folder_path.join(original_filename);
-    let mut counter: u32 = 1;
+    let filename: String = sanitize_filename(&attachment.filename);
+    let blob_name: String = match split_extension(&filename) {
+        (_, Some(extension)) => format!("{}.{}", sha256, extension.to_lowercase()),
+        (_, None) => sha256.to_string(),
+    };
+    let file_path: PathBuf = folder_path.join(blob_name);
+    ensure_within_folder(folder_path, &file_path)?;
+
+    fs::write(&file_path, content)?;
+
+    Ok(file_path)
+}
+
+/// Sets the modification time of a written file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+/// * `modified` - The timestamp to set, typically when the owning message was sent.
+///
+/// # Errors
+///
+/// This function will return an error if the file's times cannot be updated.
+pub fn set_file_modified_time(path: &Path, modified: DateTime<FixedOffset>) -> Result<(), ExtractorError> {
+    let mtime: FileTime = FileTime::from_unix_time(modified.timestamp(), modified.timestamp_subsec_nanos());
+    filetime::set_file_mtime(path, mtime)?;
+    Ok(())
+}
 
-    if file_path.exists() {
-        let stem: String = file_path.file_stem().unwrap().to_str().unwrap().to_string();
-        let extension: String = file_path.extension().unwrap().to_str().unwrap().to_string();
+/// Verifies that `file_path` resolves to a location inside `folder`.
+///
+/// # Errors
+///
+/// This function will return an `ExtractorError::InvalidPath` if the path contains anything but
+/// plain components below `folder`, or if its parent lies outside `folder` after resolving symbolic links.
+fn ensure_within_folder(folder: &Path, file_path: &Path) -> Result<(), ExtractorError> {
+    let invalid = || ExtractorError::InvalidPath(file_path.to_path_buf());
+
+    let relative: &Path = file_path.strip_prefix(folder).map_err(|_| invalid())?;
+    if relative.as_os_str().is_empty() || !relative.components().all(|c: Component| matches!(c, Component::Normal(_))) {
+        return Err(invalid());
+    }
+
+    let canonical_folder: PathBuf = folder.canonicalize()?;
+    let canonical_parent: PathBuf = file_path.parent().ok_or_else(invalid)?.canonicalize()?;
+    if !canonical_parent.starts_with(&canonical_folder) {
+        return Err(invalid());
+    }
 
-        while file_path.exists() {
-            counter += 1;
-            let new_filename: String = format!("{}({}).{}", stem, counter, extension);
-            file_path = folder_path.join(new_filename);
+    Ok(())
+}
+
+/// Expresses a written path relative to the output folder, using `/` as separator.
+pub fn relative_path(path: &Path, output_path: &Path) -> String {
+    let relative: &Path = path.strip_prefix(output_path).unwrap_or(path);
+    relative.components()
+        .map(|c: Component| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Percent-encodes a relative path for use in a URL, keeping its `/` separators
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded: String = String::new();
+    for byte in path.replace('\\', "/").bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
         }
     }
+    encoded
+}
 
-    let decoded_content: Vec<u8> = BASE64_STANDARD.decode(&attachment.content)?;
+/// Converts an output path into a string slice.
+///
+/// # Errors
+///
+/// This function will return an `ExtractorError::InvalidPath` if the path is not valid UTF-8.
+pub fn path_to_str(path: &Path) -> Result<&str, ExtractorError> {
+    path.to_str().ok_or_else(|| ExtractorError::InvalidPath(path.to_path_buf()))
+}
 
-    fs::write(&file_path, decoded_content)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
-}
\ No newline at end of file
+    /// Creates an empty folder for a test below the system temporary folder.
+    fn test_folder(name: &str) -> PathBuf {
+        let folder: PathBuf = std::env::temp_dir().join(format!("gchat_mbox_extractor_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn sanitize_drops_directory_parts() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("..\\..\\Windows\\win.ini"), "win.ini");
+        assert_eq!(sanitize_filename("/etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("C:\\Users\\me\\report.pdf"), "report.pdf");
+        assert_eq!(sanitize_filename(".."), "attachment");
+        assert_eq!(sanitize_filename("folder/"), "attachment");
+    }
+
+    #[test]
+    fn sanitize_replaces_reserved_characters() {
+        assert_eq!(sanitize_filename("a<b>c:d\"e|f?g*h.txt"), "a_b_c_d_e_f_g_h.txt");
+        assert_eq!(sanitize_filename("line\nbreak.txt"), "line_break.txt");
+        assert_eq!(sanitize_filename(" .hidden. "), "hidden");
+    }
+
+    #[test]
+    fn sanitize_prefixes_windows_device_names() {
+        assert_eq!(sanitize_filename("CON.txt"), "_CON.txt");
+        assert_eq!(sanitize_filename("con"), "_con");
+        assert_eq!(sanitize_filename("Lpt1.tar.gz"), "_Lpt1.tar.gz");
+        assert_eq!(sanitize_filename("CONSOLE.txt"), "CONSOLE.txt");
+    }
+
+    #[test]
+    fn sanitize_truncates_overlong_names() {
+        let sanitized: String = sanitize_filename(&format!("{}.pdf", "a".repeat(300)));
+        assert_eq!(sanitized.len(), MAX_FILENAME_LENGTH);
+        assert!(sanitized.ends_with(".pdf"));
+
+        let sanitized: String = sanitize_filename(&format!("{}.txt", "é".repeat(150)));
+        assert!(sanitized.len() <= MAX_FILENAME_LENGTH);
+        assert!(sanitized.ends_with(".txt"));
+
+        let sanitized: String = sanitize_filename(&format!("a.{}", "x".repeat(300)));
+        assert_eq!(sanitized.len(), MAX_FILENAME_LENGTH);
+        assert!(sanitized.starts_with("a.xxx"));
+
+        let sanitized: String = sanitize_filename(&format!("{}.{}", "a".repeat(250), "x".repeat(40)));
+        assert_eq!(sanitized, "a".repeat(MAX_FILENAME_LENGTH));
+
+        let sanitized: String = sanitize_filename(&format!("{}. .pdf", "a".repeat(196)));
+        assert_eq!(sanitized, format!("{}.pdf", "a".repeat(196)));
+    }
+
+    #[test]
+    fn ensure_within_folder_accepts_plain_paths() {
+        let folder: PathBuf = test_folder("within");
+        fs::create_dir_all(folder.join("sub")).unwrap();
+
+        assert!(ensure_within_folder(&folder, &folder.join("file.txt")).is_ok());
+        assert!(ensure_within_folder(&folder, &folder.join("sub").join("file.txt")).is_ok());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn ensure_within_folder_rejects_escaping_paths() {
+        let folder: PathBuf = test_folder("escape");
+
+        assert!(ensure_within_folder(&folder, &folder.join("..").join("file.txt")).is_err());
+        assert!(ensure_within_folder(&folder, Path::new("/etc/passwd")).is_err());
+        assert!(ensure_within_folder(&folder, &folder).is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ensure_within_folder_rejects_symlinked_parents() {
+        let folder: PathBuf = test_folder("symlink");
+        let outside: PathBuf = test_folder("symlink_target");
+        std::os::unix::fs::symlink(&outside, folder.join("link")).unwrap();
+
+        assert!(ensure_within_folder(&folder, &folder.join("link").join("file.txt")).is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+
+    #[test]
+    fn write_attachment_rejects_traversal() {
+        let folder: PathBuf = test_folder("write");
+        let folder_str: &str = path_to_str(&folder).unwrap();
+
+        assert!(write_attachment_to_file(Path::new("../escape.txt"), b"x", folder_str).is_err());
+        assert!(write_attachment_to_file(Path::new("/tmp/escape.txt"), b"x", folder_str).is_err());
+        let written: PathBuf = write_attachment_to_file(Path::new("sub/ok.txt"), b"x", folder_str).unwrap();
+        assert_eq!(written, folder.join("sub").join("ok.txt"));
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use serde::Serialize;
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, Diagnostic, DiagnosticKind, Diagnostics};
+use std::collections::BTreeMap;
+
+/// The document written to `diagnostics.json`.
+#[derive(Serialize)]
+struct DiagnosticsReport {
+    counts: BTreeMap<DiagnosticKind, usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Writes every recorded diagnostic, ordered by byte offset, to a JSON file.
+///
+/// # Arguments
+///
+/// * `diagnostics` - The collector holding the diagnostics of the run.
+/// * `filename` - A string slice specifying the name of the output JSON file.
+///
+/// # Returns
+///
+/// A Result indicating success (Ok(())) or failure (Err) in writing the JSON file.
+///
+/// # Errors
+///
+/// This function will return an error if creating the file, serializing or writing to it fails.
+pub fn write_diagnostics_to_json(diagnostics: &Diagnostics, filename: &str) -> Result<(), ExtractorError> {
+    let report: DiagnosticsReport = DiagnosticsReport {
+        counts: diagnostics.counts(),
+        diagnostics: diagnostics.sorted(),
+    };
+
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(filename)?);
+    serde_json::to_writer_pretty(&mut writer, &report)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes the attachment manifest as a JSON array.
+///
+/// # Arguments
+///
+/// * `attachments` - A slice of AttachmentRecord structs to be written to the JSON file.
+/// * `filename` - A string slice specifying the name of the output JSON file.
+///
+/// # Returns
+///
+/// A Result indicating success (Ok(())) or failure (Err) in writing the JSON file.
+///
+/// # Errors
+///
+/// This function will return an error if creating the file, serializing or writing to it fails.
+pub fn write_attachments_to_json(attachments: &[AttachmentRecord], filename: &str) -> Result<(), ExtractorError> {
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(filename)?);
+    serde_json::to_writer_pretty(&mut writer, attachments)?;
+    writer.flush()?;
+
+    Ok(())
+}
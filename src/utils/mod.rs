@@ -1,13 +1,29 @@
+#[cfg(feature = "parquet")]
+mod arrow_utils;
 mod file_utils;
 mod csv_utils;
+mod json_utils;
+mod path_template;
+#[cfg(feature = "xlsx")]
+mod xlsx_utils;
 
-pub use file_utils::{read_mbox_file, write_attachment_to_file};
-pub use csv_utils::write_messages_to_csv;
+pub use file_utils::{
+    read_mbox_file, decode_attachment, sha256_hex, sniff_content_type, write_attachment_to_file, write_attachment_blob,
+    set_file_modified_time, relative_path, percent_encode_path, path_to_str,
+};
+pub use csv_utils::{write_messages_to_csv, write_attachments_to_csv};
+pub use json_utils::{write_diagnostics_to_json, write_attachments_to_json};
+pub use path_template::{render_attachment_path, DEFAULT_ATTACHMENT_TEMPLATE};
+#[cfg(feature = "xlsx")]
+pub use xlsx_utils::{write_workbook_to_xlsx, SummaryValue};
+
+#[cfg(feature = "parquet")]
+pub use arrow_utils::{message_schema, attachment_schema, messages_to_record_batch, attachments_to_record_batch};
 
 use quoted_printable::ParseMode;
-use std::error::Error;
+use crate::error::ExtractorError;
 
-pub fn decode_quoted_printable(input: &str) -> Result<String, Box<dyn Error>> {
+pub fn decode_quoted_printable(input: &str) -> Result<String, ExtractorError> {
     let decoded: Vec<u8> = quoted_printable::decode(input.as_bytes(), ParseMode::Robust)?;
     Ok(String::from_utf8_lossy(&decoded).to_string())
-}
\ No newline at end of file
+}
@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+use chrono::{DateTime, FixedOffset};
+use regex::{Captures, Regex};
+use crate::models::{Attachment, MboxEntry, Message};
+use super::file_utils::sanitize_filename;
+
+/// The template producing the original flat `attachments/<filename>` layout.
+pub const DEFAULT_ATTACHMENT_TEMPLATE: &str = "{filename}";
+
+/// Renders an attachment path template for a single attachment.
+///
+/// The template is split on `/` into folders, the last segment being the file name. The following
+/// placeholders are replaced with the metadata of the owning message:
+///
+/// * `{space}` - The space or direct message name (see `MboxEntry::space`).
+/// * `{yyyy}`, `{mm}`, `{dd}` - The date the message was sent, falling back to the entry's `Date` header.
+/// * `{sender}` - The sender of the message.
+/// * `{message_id}` - The id of the message.
+/// * `{entry}` - The index of the .mbox entry.
+/// * `{filename}` - The sanitized attachment filename (see `sanitize_filename`).
+///
+/// Unknown placeholders are kept verbatim. Path separators inside the metadata values are replaced with `_`,
+/// so a space such as `R&D / Ops` stays a single folder, while `{filename}` keeps only its last component,
+/// like every attachment filename, so `{entry}_{filename}` keeps its prefix. Every rendered segment is then
+/// passed through `sanitize_filename`, so the result never contains `..`, absolute paths or reserved names.
+///
+/// # Arguments
+///
+/// * `template` - A string slice containing the template, e.g. `{space}/{yyyy}/{mm}/{sender}/{filename}`.
+/// * `entry` - The .mbox entry the attachment belongs to.
+/// * `message` - The message owning the attachment, if any.
+/// * `attachment` - The attachment being written.
+///
+/// # Returns
+///
+/// A relative `PathBuf` with at least one component.
+pub fn render_attachment_path(template: &str, entry: &MboxEntry, message: Option<&Message>, attachment: &Attachment) -> PathBuf {
+    let placeholder_regex: Regex = Regex::new(r"\{(\w+)\}").unwrap();
+    let date: Option<DateTime<FixedOffset>> = entry.sent_at(message);
+    let format_date = |format: &str| date.map(|d: DateTime<FixedOffset>| d.format(format).to_string()).unwrap_or_else(|| "undated".to_string());
+
+    let segments: Vec<&str> = template.split(['/', '\\'])
+        .map(str::trim)
+        .filter(|segment: &&str| !segment.is_empty())
+        .collect();
+    if segments.is_empty() {
+        return PathBuf::from(sanitize_filename(&attachment.filename));
+    }
+
+    segments.iter()
+        .map(|segment: &&str| {
+            let rendered = placeholder_regex.replace_all(segment, |caps: &Captures| match &caps[1] {
+                "space" => replace_separators(&entry.space()),
+                "yyyy" => format_date("%Y"),
+                "mm" => format_date("%m"),
+                "dd" => format_date("%d"),
+                "sender" => message.map(|m: &Message| replace_separators(&m.sender)).unwrap_or_else(|| "Unknown sender".to_string()),
+                "message_id" => message.map(|m: &Message| replace_separators(&m.message_id)).unwrap_or_default(),
+                "entry" => entry.index.to_string(),
+                "filename" => sanitize_filename(&attachment.filename),
+                _ => caps[0].to_string(),
+            });
+            sanitize_filename(&rendered)
+        })
+        .collect()
+}
+
+/// Helper function to keep a placeholder value within a single path segment
+fn replace_separators(value: &str) -> String {
+    value.replace(['/', '\\'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(date: &str) -> MboxEntry {
+        MboxEntry {
+            index: 7,
+            byte_offset: 0,
+            headers: format!("123@xxx Mon Jan 01 00:00:00 2024\nSubject: R&D / Ops\nDate: {}", date),
+            html_body: String::new(),
+            attachments: Vec::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    fn message(timestamp: &str) -> Message {
+        Message {
+            message_id: "abc/def".to_string(),
+            sender: "Jane Doe".to_string(),
+            timestamp: timestamp.to_string(),
+            content: String::new(),
+        }
+    }
+
+    fn attachment(filename: &str) -> Attachment {
+        Attachment { content_type: "image/jpeg".to_string(), filename: filename.to_string(), content: String::new() }
+    }
+
+    #[test]
+    fn renders_mixed_segments() {
+        let path: PathBuf = render_attachment_path(
+            "{space}/{yyyy}-{mm}-{dd}/{sender} {message_id}/{entry}_{filename}",
+            &entry("Mon, 1 Jan 2024 00:00:00 +0000"),
+            Some(&message("2023-05-06T07:08:09+00:00")),
+            &attachment("photos/a.jpg"),
+        );
+        assert_eq!(path, PathBuf::from("R&D _ Ops/2023-05-06/Jane Doe abc_def/7_a.jpg"));
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        let path: PathBuf = render_attachment_path("{unknown}/{filename}", &entry(""), None, &attachment("a.jpg"));
+        assert_eq!(path, PathBuf::from("{unknown}/a.jpg"));
+    }
+
+    #[test]
+    fn empty_template_uses_the_filename() {
+        let path: PathBuf = render_attachment_path("", &entry(""), None, &attachment("../photos/a.jpg"));
+        assert_eq!(path, PathBuf::from("a.jpg"));
+        let path: PathBuf = render_attachment_path(" / ", &entry(""), None, &attachment("a.jpg"));
+        assert_eq!(path, PathBuf::from("a.jpg"));
+    }
+
+    #[test]
+    fn dot_segments_stay_inside() {
+        let path: PathBuf = render_attachment_path("../{space}/./../{filename}", &entry(""), None, &attachment("..\\a.jpg"));
+        assert_eq!(path, PathBuf::from("attachment/R&D _ Ops/attachment/attachment/a.jpg"));
+        assert!(path.components().all(|c: std::path::Component| matches!(c, std::path::Component::Normal(_))));
+    }
+
+    #[test]
+    fn undated_entries_fall_back() {
+        let path: PathBuf = render_attachment_path("{yyyy}/{mm}/{sender}/{filename}", &entry("not a date"), None, &attachment("a.jpg"));
+        assert_eq!(path, PathBuf::from("undated/undated/Unknown sender/a.jpg"));
+
+        let path: PathBuf = render_attachment_path(
+            "{yyyy}/{mm}/{dd}/{filename}",
+            &entry("Tue, 2 Jan 2024 10:00:00 +0000"),
+            Some(&message("not a timestamp")),
+            &attachment("a.jpg"),
+        );
+        assert_eq!(path, PathBuf::from("2024/01/02/a.jpg"));
+    }
+}
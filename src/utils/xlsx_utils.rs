@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use rust_xlsxwriter::{Format, Url, Workbook, Worksheet};
+use crate::error::ExtractorError;
+use crate::models::{AttachmentRecord, Message};
+use super::file_utils::percent_encode_path;
+
+/// The maximum number of characters Excel accepts in a single cell.
+const MAX_CELL_LENGTH: usize = 32_767;
+
+/// The maximum number of hyperlinks Excel accepts in a single worksheet.
+const MAX_LINKS_PER_SHEET: usize = 65_530;
+
+/// A value of the Summary sheet: counts are written as numbers, so they can be summed and formatted.
+pub enum SummaryValue {
+    Text(String),
+    Count(usize),
+}
+
+/// The columns of the Messages sheets with their widths; stored attachments continue to the right of the last one.
+const MESSAGE_HEADER: [(&str, f64); 6] = [
+    ("message_id", 24.0),
+    ("space", 24.0),
+    ("sender", 24.0),
+    ("sent_at (UTC)", 20.0),
+    ("content", 80.0),
+    ("attachments", 30.0),
+];
+
+/// The columns of the Attachments sheets with their widths.
+const ATTACHMENT_HEADER: [(&str, f64); 11] = [
+    ("original_filename", 30.0),
+    ("message_id", 24.0),
+    ("entry_index", 12.0),
+    ("stored_path", 40.0),
+    ("declared_content_type", 24.0),
+    ("sniffed_content_type", 24.0),
+    ("size", 12.0),
+    ("sha256", 66.0),
+    ("type_mismatch", 14.0),
+    ("duplicate", 12.0),
+    ("filter_reason", 40.0),
+];
+
+/// Writes an Excel workbook with a Messages, an Attachments and a Summary sheet.
+///
+/// Timestamps become real datetime cells, content is wrapped and stored attachments are linked
+/// relative to the workbook, so the workbook must be saved in the output folder. Sheets holding more
+/// rows than `max_rows_per_sheet` continue on `Messages (2)`, `Messages (3)`, ...
+///
+/// # Arguments
+///
+/// * `messages` - The messages to list, each with the name of its space.
+/// * `attachments` - The attachment records to list and link from their messages.
+/// * `summary` - The label/value pairs of the Summary sheet.
+/// * `filename` - A string slice specifying the name of the output workbook.
+/// * `max_rows_per_sheet` - The maximum number of rows, header included, of a sheet (see `EXCEL_MAX_ROWS`).
+///
+/// # Returns
+///
+/// A Result indicating success (Ok(())) or failure (Err) in writing the workbook.
+///
+/// # Errors
+///
+/// This function will return an error if a cell cannot be written or the workbook cannot be saved.
+pub fn write_workbook_to_xlsx(
+    messages: &[(String, Message)],
+    attachments: &[AttachmentRecord],
+    summary: &[(String, SummaryValue)],
+    filename: &str,
+    max_rows_per_sheet: usize,
+) -> Result<(), ExtractorError> {
+    let header_format: Format = Format::new().set_bold();
+    let wrap_format: Format = Format::new().set_text_wrap();
+    let datetime_format: Format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+    let rows_per_sheet: usize = max_rows_per_sheet.max(2) - 1;
+
+    let mut stored_attachments: HashMap<&str, Vec<&AttachmentRecord>> = HashMap::new();
+    for record in attachments.iter().filter(|record: &&AttachmentRecord| !record.stored_path.is_empty()) {
+        stored_attachments.entry(record.message_id.as_str()).or_default().push(record);
+    }
+
+    let mut workbook: Workbook = Workbook::new();
+
+    // Messages sheets
+    for (part, chunk) in chunks_or_empty(messages, rows_per_sheet).iter().enumerate() {
+        let mut worksheet: Worksheet = new_sheet("Messages", part, &MESSAGE_HEADER, &header_format)?;
+        let mut links: usize = 0;
+        for (index, (space, message)) in chunk.iter().enumerate() {
+            let row: u32 = index as u32 + 1;
+            worksheet.write_string(row, 0, &message.message_id)?;
+            worksheet.write_string(row, 1, space)?;
+            worksheet.write_string(row, 2, &message.sender)?;
+            match message.datetime() {
+                Some(datetime) => worksheet.write_datetime_with_format(row, 3, datetime.naive_utc(), &datetime_format)?,
+                None => worksheet.write_string(row, 3, &message.timestamp)?,
+            };
+            worksheet.write_string_with_format(row, 4, truncate_cell(&message.content), &wrap_format)?;
+
+            let records: &[&AttachmentRecord] = stored_attachments.get(message.message_id.as_str()).map_or(&[], Vec::as_slice);
+            for (offset, record) in records.iter().enumerate() {
+                write_link(&mut worksheet, row, (5 + offset) as u16, record, &mut links)?;
+            }
+        }
+        workbook.push_worksheet(worksheet);
+    }
+
+    // Attachments sheets
+    for (part, chunk) in chunks_or_empty(attachments, rows_per_sheet).iter().enumerate() {
+        let mut worksheet: Worksheet = new_sheet("Attachments", part, &ATTACHMENT_HEADER, &header_format)?;
+        let mut links: usize = 0;
+        for (index, record) in chunk.iter().enumerate() {
+            let row: u32 = index as u32 + 1;
+            write_link(&mut worksheet, row, 0, record, &mut links)?;
+            worksheet.write_string(row, 1, &record.message_id)?;
+            worksheet.write_number(row, 2, record.entry_index as f64)?;
+            worksheet.write_string(row, 3, &record.stored_path)?;
+            worksheet.write_string(row, 4, &record.declared_content_type)?;
+            worksheet.write_string(row, 5, &record.sniffed_content_type)?;
+            worksheet.write_number(row, 6, record.size as f64)?;
+            worksheet.write_string(row, 7, &record.sha256)?;
+            worksheet.write_boolean(row, 8, record.type_mismatch)?;
+            worksheet.write_boolean(row, 9, record.duplicate)?;
+            worksheet.write_string(row, 10, &record.filter_reason)?;
+        }
+        workbook.push_worksheet(worksheet);
+    }
+
+    // Summary sheet
+    let mut worksheet: Worksheet = new_sheet("Summary", 0, &[("item", 30.0), ("value", 60.0)], &header_format)?;
+    for (index, (label, value)) in summary.iter().enumerate() {
+        let row: u32 = index as u32 + 1;
+        worksheet.write_string(row, 0, label)?;
+        match value {
+            SummaryValue::Text(text) => worksheet.write_string(row, 1, truncate_cell(text))?,
+            SummaryValue::Count(count) => worksheet.write_number(row, 1, *count as f64)?,
+        };
+    }
+    workbook.push_worksheet(worksheet);
+
+    workbook.save(filename)?;
+    Ok(())
+}
+
+/// Creates a worksheet named after its part number, with a bold, frozen header row and an autofilter
+fn new_sheet(name: &str, part: usize, header: &[(&str, f64)], header_format: &Format) -> Result<Worksheet, ExtractorError> {
+    let mut worksheet: Worksheet = Worksheet::new();
+    if part == 0 {
+        worksheet.set_name(name)?;
+    } else {
+        worksheet.set_name(format!("{} ({})", name, part + 1))?;
+    }
+    for (column, (title, width)) in header.iter().enumerate() {
+        worksheet.write_string_with_format(0, column as u16, *title, header_format)?;
+        worksheet.set_column_width(column as u16, *width)?;
+    }
+    worksheet.set_freeze_panes(1, 0)?;
+    worksheet.autofilter(0, 0, 0, header.len() as u16 - 1)?;
+    Ok(worksheet)
+}
+
+/// Writes the filename of an attachment, linked to its stored file while the sheet still accepts links
+fn write_link(
+    worksheet: &mut Worksheet,
+    row: u32,
+    column: u16,
+    record: &AttachmentRecord,
+    links: &mut usize,
+) -> Result<(), ExtractorError> {
+    if !record.stored_path.is_empty() && *links < MAX_LINKS_PER_SHEET {
+        let url: Url = Url::new(format!("file:///{}", percent_encode_path(&record.stored_path)));
+        if worksheet.write_url_with_text(row, column, url, &record.original_filename).is_ok() {
+            *links += 1;
+            return Ok(());
+        }
+    }
+    worksheet.write_string(row, column, &record.original_filename)?;
+    Ok(())
+}
+
+/// Helper function to split rows across sheets, keeping one (empty) sheet when there are no rows
+fn chunks_or_empty<T>(rows: &[T], rows_per_sheet: usize) -> Vec<&[T]> {
+    if rows.is_empty() {
+        return vec![rows];
+    }
+    rows.chunks(rows_per_sheet).collect()
+}
+
+/// Helper function to cut a value down to the length Excel accepts in a cell
+fn truncate_cell(value: &str) -> &str {
+    match value.char_indices().nth(MAX_CELL_LENGTH) {
+        Some((index, _)) => &value[..index],
+        None => value,
+    }
+}
+